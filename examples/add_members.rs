@@ -11,8 +11,12 @@ use whatsapp_rust::store::SqliteStore;
 use whatsapp_rust_tokio_transport::TokioWebSocketTransportFactory;
 use whatsapp_rust_ureq_http_client::UreqHttpClient;
 
+use whatsapp_invites::config::RunConfig;
+use whatsapp_invites::connection;
 use whatsapp_invites::groups::GroupManagement;
-use whatsapp_invites::member_utils::{add_members_batch, finalize_member_addition};
+use whatsapp_invites::member_utils::{
+    add_members_batch, finalize_member_addition, parse_member_jid,
+};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -64,7 +68,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let participant_jids: Vec<Jid> = phone_numbers
         .iter()
-        .map(|phone| format!("{}@s.whatsapp.net", phone).parse())
+        .map(|phone| parse_member_jid(phone))
         .collect::<Result<Vec<_>, _>>()?;
 
     println!(
@@ -105,22 +109,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 image
                             );
                         }
+                        Event::Disconnected(_) => connection::mark_disconnected(),
                         Event::Connected(_) => {
-                            println!("Bot connected! Fetching group info...\n");
-
-                            if let Ok(metadata) = client.query_group_metadata(&group_jid).await {
-                                println!("=== Group Information ===");
-                                println!("Group Name: {}", metadata.subject);
-                                println!("Current Participants: {}", metadata.participant_count);
-                                println!("Group JID: {}", group_jid);
-                                println!();
+                            if !connection::mark_connected() {
+                                println!("Reconnected");
+                                return;
                             }
 
-                            let stats =
-                                add_members_batch(&client, &group_jid, &participant_jids, 5).await;
-
-                            finalize_member_addition(&client, &group_jid, stats).await;
-                            std::process::exit(0);
+                            tokio::spawn(async move {
+                                println!("Bot connected! Fetching group info...\n");
+
+                                if let Ok(metadata) = client.query_group_metadata(&group_jid).await
+                                {
+                                    println!("=== Group Information ===");
+                                    println!("Group Name: {}", metadata.subject);
+                                    println!(
+                                        "Current Participants: {}",
+                                        metadata.participant_count
+                                    );
+                                    if metadata.is_community {
+                                        println!(
+                                            "Community with {} linked sub-group(s):",
+                                            metadata.linked_subgroups.len()
+                                        );
+                                        for subgroup in &metadata.linked_subgroups {
+                                            println!("  - {}", subgroup);
+                                        }
+                                    }
+                                    println!("Group JID: {}", group_jid);
+                                    println!();
+                                }
+
+                                let config = RunConfig::default();
+                                let stats = add_members_batch(
+                                    client.as_ref(),
+                                    &group_jid,
+                                    &participant_jids,
+                                    &config,
+                                )
+                                .await;
+
+                                finalize_member_addition(
+                                    client.as_ref(),
+                                    &group_jid,
+                                    stats,
+                                    &config,
+                                )
+                                .await;
+                                std::process::exit(0);
+                            });
                         }
                         _ => {}
                     }
@@ -72,6 +72,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         if let Some(info) = group_info {
                             println!("   Group Name: {}", info.subject);
                             println!("   Participants: {}", info.participant_count);
+                            for subgroup in &info.linked_subgroups {
+                                println!("   Linked sub-group: {}", subgroup);
+                            }
                         }
                         println!("   Group JID: {}", from);
                         println!("   Sender: {}", sender);
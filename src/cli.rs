@@ -0,0 +1,153 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Parsed command-line arguments: positional arguments plus `--name [value]` options
+#[derive(Debug, Default, Clone)]
+pub struct CliArgs {
+    pub positional: Vec<String>,
+    options: Vec<(String, Option<String>)>,
+}
+
+impl CliArgs {
+    /// Parse arguments (without the program name)
+    ///
+    /// # Arguments
+    /// * `args` - The raw arguments, e.g. `std::env::args().skip(1)`
+    /// * `switches` - Option names (without `--`) that never take a value
+    ///
+    /// Options accept both `--name value` and `--name=value`. Everything after a
+    /// bare `--` is treated as positional.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        switches: &[&str],
+    ) -> Result<Self, String> {
+        let mut parsed = CliArgs::default();
+        let mut args = args.into_iter();
+        let mut only_positional = false;
+
+        while let Some(arg) = args.next() {
+            if only_positional || !arg.starts_with("--") {
+                parsed.positional.push(arg);
+                continue;
+            }
+
+            if arg == "--" {
+                only_positional = true;
+                continue;
+            }
+
+            let option = &arg[2..];
+            if let Some((name, value)) = option.split_once('=') {
+                parsed
+                    .options
+                    .push((name.to_string(), Some(value.to_string())));
+            } else if switches.contains(&option) {
+                parsed.options.push((option.to_string(), None));
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| format!("Option --{} requires a value", option))?;
+                parsed.options.push((option.to_string(), Some(value)));
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Whether the option was given at all
+    pub fn flag(&self, name: &str) -> bool {
+        self.options.iter().any(|(option, _)| option == name)
+    }
+
+    /// The value of the last occurrence of an option
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    /// The values of every occurrence of a repeatable option, in order
+    pub fn values(&self, name: &str) -> Vec<&str> {
+        self.options
+            .iter()
+            .filter(|(option, _)| option == name)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    /// Parse the value of an option, returning `Ok(None)` when it is absent
+    pub fn parse_value<T: FromStr>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T::Err: std::fmt::Display,
+    {
+        self.value(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| format!("Invalid value for --{}: {} ({})", name, value, e))
+            })
+            .transpose()
+    }
+}
+
+/// Parse a duration such as `90s`, `45m`, `6h` or `1h30m`
+///
+/// A bare number is read as minutes.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration \"{}\" (expected e.g. 90s, 45m, 6h or 1h30m)",
+            input
+        )
+    };
+
+    if let Ok(mins) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(mins * 60));
+    }
+
+    let mut total = 0;
+    let mut number = String::new();
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total += match c {
+            's' => value,
+            'm' => value * 60,
+            'h' => value * 3600,
+            _ => return Err(invalid()),
+        };
+        number.clear();
+    }
+
+    if !number.is_empty() || total == 0 {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("6h"), Ok(Duration::from_secs(6 * 3600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45 * 60)));
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("6d").is_err());
+        assert!(parse_duration("1h30").is_err());
+    }
+}
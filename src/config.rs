@@ -0,0 +1,448 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use wacore_binary::jid::Jid;
+
+use crate::report::DEFAULT_REPORT_FILE;
+
+/// Settings shared by a member-addition run
+///
+/// The group IQ timeout is process-wide rather than per run; set it with
+/// `groups::set_iq_timeout`.
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    /// Seconds to wait between two members
+    pub delay_seconds: u64,
+    /// Maximum retry attempts for rate-limited (429) adds
+    pub max_retries: usize,
+    /// Milliseconds to wait between two invite messages
+    pub invite_delay_ms: u64,
+    /// Path of the invite message template
+    pub message_file: String,
+    /// Language of the built-in invite template, used when `message_file` doesn't exist
+    pub invite_lang: String,
+    /// How invite messages are sent to members that couldn't be added
+    pub invite_format: InviteFormat,
+    /// Image (JPEG or PNG) attached to text invites, with the message as caption
+    pub invite_image: Option<String>,
+    /// DM an invite link to members that couldn't be added
+    pub send_invite: bool,
+    /// Path of the run report
+    pub report_file: String,
+    /// Also write the run report as CSV to this path
+    pub report_csv: Option<String>,
+    /// Write Prometheus-style metrics of the run to this path when done
+    pub metrics_file: Option<String>,
+    /// Append one JSON line per add attempt to this path, across runs
+    pub audit_log: Option<String>,
+    /// POST the final run report to this URL
+    pub webhook: Option<String>,
+    /// Write the in-progress run report every this many members (0 = only at the end)
+    pub checkpoint_every: usize,
+    /// Spread the adds evenly over this window instead of using `delay_seconds`
+    pub spread_over: Option<Duration>,
+    /// Stop starting new adds once the batch has run this long
+    pub max_duration: Option<Duration>,
+    /// Campaign label recorded in the run report, added_members.json and metrics
+    pub label: String,
+    /// Seed for a randomized run: shuffled member order and jittered delays
+    pub shuffle_seed: Option<u64>,
+    /// Only add members already in this reference group
+    pub only_from: Option<Jid>,
+    /// Only add these normalized phones (`--require-consent-file`); others are refused
+    pub consented_phones: Option<HashSet<String>>,
+    /// Skip members added to this group by an earlier run (per added_members.json)
+    pub skip_previously_added: bool,
+    /// Re-check the participant list on a 409 and retry once if the member is absent
+    pub verify_409: bool,
+    /// Re-query the participants after the batch and report adds that didn't take effect
+    pub verify_adds: bool,
+    /// Membership TTL sent with every add, for groups with time-limited membership
+    pub member_ttl: Option<Duration>,
+    /// Stop the batch when the first add fails with 403 and the account isn't a group admin
+    pub abort_on_not_admin: bool,
+    /// Stop the batch after this many failed adds in a row (skips don't count)
+    pub max_consecutive_failures: Option<usize>,
+    /// Role given to every member added by the run, once all adds are done
+    pub assign_role: Option<Role>,
+    /// Message posted once in the group after the batch if anyone was added (`{count}` = added)
+    pub welcome_message: Option<String>,
+    /// Delay and backoff overrides keyed by phone prefix (country code)
+    pub country_profiles: BTreeMap<String, CountryProfile>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            delay_seconds: 5,
+            max_retries: 2,
+            invite_delay_ms: 500,
+            message_file: "message.txt".to_string(),
+            invite_lang: "en".to_string(),
+            invite_format: InviteFormat::default(),
+            invite_image: None,
+            send_invite: true,
+            report_file: DEFAULT_REPORT_FILE.to_string(),
+            report_csv: None,
+            metrics_file: None,
+            audit_log: None,
+            webhook: None,
+            checkpoint_every: 25,
+            spread_over: None,
+            max_duration: None,
+            label: String::new(),
+            shuffle_seed: None,
+            only_from: None,
+            consented_phones: None,
+            skip_previously_added: false,
+            verify_409: false,
+            verify_adds: false,
+            member_ttl: None,
+            abort_on_not_admin: true,
+            max_consecutive_failures: None,
+            assign_role: None,
+            welcome_message: None,
+            country_profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl RunConfig {
+    /// The retry policy applied to each member of the run
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            verify_409: self.verify_409,
+            member_ttl: self.member_ttl,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// The country profile matching a phone number, if any
+    ///
+    /// When several prefixes match, the longest one wins.
+    pub fn country_profile(&self, phone: &str) -> Option<&CountryProfile> {
+        self.country_profiles
+            .iter()
+            .filter(|(prefix, _)| phone.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, profile)| profile)
+    }
+
+    /// Seconds to wait before adding a member with this phone number
+    pub fn delay_for(&self, phone: &str) -> u64 {
+        self.country_profile(phone)
+            .and_then(|profile| profile.delay_secs)
+            .unwrap_or(self.delay_seconds)
+    }
+
+    /// The retry policy applied to a member with this phone number
+    pub fn retry_policy_for(&self, phone: &str) -> RetryPolicy {
+        let mut policy = self.retry_policy();
+        if let Some(profile) = self.country_profile(phone) {
+            if let Some(max_retries) = profile.max_retries {
+                policy.max_retries = max_retries;
+            }
+            if let Some(rate_limit_wait_secs) = profile.rate_limit_wait_secs {
+                policy.rate_limit_wait = Duration::from_secs(rate_limit_wait_secs);
+            }
+        }
+        policy
+    }
+}
+
+/// Pacing overrides for members whose phone starts with a given prefix
+///
+/// Absent fields fall back to the run-wide values.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CountryProfile {
+    /// Seconds to wait before adding a member
+    pub delay_secs: Option<u64>,
+    /// Seconds to wait before retrying a rate-limited (429) member
+    pub rate_limit_wait_secs: Option<u64>,
+    /// Maximum retry attempts for rate-limited (429) adds
+    pub max_retries: Option<usize>,
+}
+
+/// Limits used by `--estimate` to grade the rate-limit risk of a batch
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskThresholds {
+    /// Delays shorter than this many seconds raise the risk
+    pub safe_delay_secs: u64,
+    /// Batches of more adds than this raise the risk
+    pub large_batch: usize,
+    /// Starting sooner than this many hours after the previous run raises the risk
+    pub min_hours_between_runs: u64,
+}
+
+impl Default for RiskThresholds {
+    fn default() -> Self {
+        Self {
+            safe_delay_secs: 15,
+            large_batch: 200,
+            min_hours_between_runs: 6,
+        }
+    }
+}
+
+/// How invite messages are sent
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InviteFormat {
+    /// A plain text message containing the invite link
+    #[default]
+    Text,
+    /// WhatsApp's tappable group invite card, captioned with the message text
+    Card,
+}
+
+impl std::str::FromStr for InviteFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(InviteFormat::Text),
+            "card" => Ok(InviteFormat::Card),
+            _ => Err("expected \"text\" or \"card\"".to_string()),
+        }
+    }
+}
+
+/// Role assigned to members after they were added
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Promote to group admin
+    Admin,
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "admin" => Ok(Role::Admin),
+            _ => Err("expected \"admin\"".to_string()),
+        }
+    }
+}
+
+/// How a single member addition is sent and retried
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum retry attempts for rate-limited (429) adds
+    pub max_retries: usize,
+    /// How long to wait after a member-scoped rate limit before retrying that member
+    pub rate_limit_wait: Duration,
+    /// How long to pause the whole batch after a global rate limit
+    pub global_pause: Duration,
+    /// Confirm a 409 ("already in group") against fresh metadata before trusting it
+    pub verify_409: bool,
+    /// Experimental membership TTL sent with the add (see `groups::MEMBER_TTLS`)
+    pub member_ttl: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 2,
+            rate_limit_wait: Duration::from_secs(30),
+            global_pause: Duration::from_secs(300),
+            verify_409: false,
+            member_ttl: None,
+        }
+    }
+}
+
+/// A campaign file describing a whole run (group, phones and options)
+///
+/// Every field is optional. An explicit command-line argument always wins over
+/// the campaign file, and absent fields fall back to the settings/env defaults.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CampaignConfig {
+    pub group_jid: Option<String>,
+    pub phones: Option<Vec<String>>,
+    pub delay_secs: Option<u64>,
+    pub message_file: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
+impl CampaignConfig {
+    /// Whether the run is a dry run: `--dry-run` wins like any other CLI argument
+    pub fn dry_run(&self, cli_dry_run: bool) -> bool {
+        cli_dry_run || self.dry_run.unwrap_or(false)
+    }
+}
+
+/// Load a campaign description from a JSON file
+pub fn load_campaign(path: &str) -> Result<CampaignConfig, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))
+}
+
+/// Name of the optional settings file
+pub const SETTINGS_FILE: &str = "whatsapp-inviter.toml";
+
+/// Defaults for repeated runs, read from `whatsapp-inviter.toml`
+///
+/// Every field is optional; CLI arguments override environment variables, which
+/// override values from the file.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub delay_secs: Option<u64>,
+    pub db_path: Option<String>,
+    pub message_file: Option<String>,
+    pub output_dir: Option<String>,
+    pub max_retries: Option<usize>,
+    pub country_code: Option<String>,
+    pub abort_on_not_admin: Option<bool>,
+    pub lid_fallback: Option<bool>,
+    pub history_max_kb: Option<u64>,
+    pub compress_history: Option<bool>,
+    pub country_profiles: BTreeMap<String, CountryProfile>,
+    pub estimate: RiskThresholds,
+}
+
+impl Settings {
+    /// Override file values with the `WA_*` environment variables that are set
+    fn apply_env(&mut self) -> Result<(), String> {
+        fn env_value<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String> {
+            match std::env::var(name) {
+                Ok(value) => value
+                    .parse()
+                    .map(Some)
+                    .map_err(|_| format!("Invalid value for {}: {}", name, value)),
+                Err(_) => Ok(None),
+            }
+        }
+
+        if let Some(delay_secs) = env_value("WA_DELAY_SECS")? {
+            self.delay_secs = Some(delay_secs);
+        }
+        if let Some(db_path) = env_value("WA_DB_PATH")? {
+            self.db_path = Some(db_path);
+        }
+        if let Some(message_file) = env_value("WA_MESSAGE_FILE")? {
+            self.message_file = Some(message_file);
+        }
+        if let Some(output_dir) = env_value("WA_OUTPUT_DIR")? {
+            self.output_dir = Some(output_dir);
+        }
+        if let Some(max_retries) = env_value("WA_MAX_RETRIES")? {
+            self.max_retries = Some(max_retries);
+        }
+        if let Some(country_code) = env_value("WA_COUNTRY_CODE")? {
+            self.country_code = Some(country_code);
+        }
+        if let Some(abort_on_not_admin) = env_value("WA_ABORT_ON_NOT_ADMIN")? {
+            self.abort_on_not_admin = Some(abort_on_not_admin);
+        }
+
+        Ok(())
+    }
+}
+
+/// Locations searched for a configuration file, in order
+///
+/// The current directory first, then `$XDG_CONFIG_HOME` (or `~/.config`).
+fn config_paths(file_name: &str) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(file_name)];
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(config_home) = config_home {
+        paths.push(config_home.join(file_name));
+    }
+
+    paths
+}
+
+/// The settings file in use: the first one found
+pub fn settings_file() -> Option<PathBuf> {
+    config_paths(SETTINGS_FILE)
+        .into_iter()
+        .find(|path| path.exists())
+}
+
+/// Load settings from the first settings file found, then apply env overrides
+///
+/// A missing file means all defaults.
+pub fn load_settings() -> Result<Settings, String> {
+    let mut settings = Settings::default();
+
+    if let Some(path) = settings_file() {
+        let data = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        settings = toml::from_str(&data)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    }
+
+    settings.apply_env()?;
+    Ok(settings)
+}
+
+/// Name of the optional account registry
+pub const ACCOUNTS_FILE: &str = "accounts.json";
+
+/// Load the account registry (account name to session database path)
+///
+/// Searched like the settings file; a missing file means no accounts.
+pub fn load_accounts() -> Result<BTreeMap<String, String>, String> {
+    let Some(path) = config_paths(ACCOUNTS_FILE)
+        .into_iter()
+        .find(|path| path.exists())
+    else {
+        return Ok(BTreeMap::new());
+    };
+
+    let data = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// The session database path registered for an account
+pub fn account_db_path(name: &str) -> Result<String, String> {
+    let accounts = load_accounts()?;
+
+    accounts.get(name).cloned().ok_or_else(|| {
+        if accounts.is_empty() {
+            format!("Unknown account \"{}\": no {} found", name, ACCOUNTS_FILE)
+        } else {
+            format!(
+                "Unknown account \"{}\" (known: {})",
+                name,
+                accounts.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dry_run_flag_overrides_the_campaign_file() {
+        let campaign = CampaignConfig {
+            dry_run: Some(false),
+            ..CampaignConfig::default()
+        };
+
+        assert!(campaign.dry_run(true));
+        assert!(!campaign.dry_run(false));
+        assert!(CampaignConfig::default().dry_run(true));
+        assert!(
+            CampaignConfig {
+                dry_run: Some(true),
+                ..CampaignConfig::default()
+            }
+            .dry_run(false)
+        );
+    }
+}
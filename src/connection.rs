@@ -0,0 +1,38 @@
+use lazy_static::lazy_static;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// How long a batch waits for the connection to come back before giving up on a member
+pub const RECONNECT_TIMEOUT: Duration = Duration::from_secs(120);
+
+static EVER_CONNECTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    /// Whether the websocket is currently connected, updated from the bot's events
+    static ref CONNECTED: watch::Sender<bool> = watch::Sender::new(false);
+}
+
+/// Record an `Event::Connected`
+///
+/// Returns true for the first connection of the process, so event handlers can
+/// start their run once and ignore later reconnects.
+pub fn mark_connected() -> bool {
+    CONNECTED.send_replace(true);
+    !EVER_CONNECTED.swap(true, Ordering::SeqCst)
+}
+
+/// Record that the connection dropped (from `Event::Disconnected` or a failed request)
+pub fn mark_disconnected() {
+    CONNECTED.send_replace(false);
+}
+
+/// Wait until the connection is back, up to `timeout`
+///
+/// Returns false when the timeout elapsed while still disconnected.
+pub async fn wait_for_reconnect(timeout: Duration) -> bool {
+    let mut receiver = CONNECTED.subscribe();
+    tokio::time::timeout(timeout, receiver.wait_for(|connected| *connected))
+        .await
+        .is_ok_and(|result| result.is_ok())
+}
@@ -1,9 +1,240 @@
 use anyhow::Result;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
 use wacore_binary::builder::NodeBuilder;
 use wacore_binary::jid::Jid;
-use wacore_binary::node::NodeContent;
+use wacore_binary::node::{Node, NodeContent};
+use waproto::whatsapp as wa;
 use whatsapp_rust::Client;
 
+/// Default timeout applied to every group IQ
+pub const DEFAULT_IQ_TIMEOUT: Duration = Duration::from_secs(30);
+
+static IQ_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(DEFAULT_IQ_TIMEOUT.as_secs());
+
+/// Override the timeout used for group IQs (add/remove/metadata/invite)
+pub fn set_iq_timeout(timeout: Duration) {
+    IQ_TIMEOUT_SECS.store(timeout.as_secs().max(1), Ordering::Relaxed);
+}
+
+/// Current timeout used for group IQs
+pub fn iq_timeout() -> Duration {
+    Duration::from_secs(IQ_TIMEOUT_SECS.load(Ordering::Relaxed))
+}
+
+static VERBOSE_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Include the raw server response in errors about unexpected response shapes
+///
+/// Off by default since responses carry participant phone numbers.
+pub fn set_verbose_errors(enabled: bool) {
+    VERBOSE_ERRORS.store(enabled, Ordering::Relaxed);
+}
+
+static LID_FALLBACK: AtomicBool = AtomicBool::new(false);
+
+/// Retry phone-number adds rejected with 400 under the member's LID, once
+///
+/// Off by default while WhatsApp migrates groups to LID-addressed participants.
+pub fn set_lid_fallback(enabled: bool) {
+    LID_FALLBACK.store(enabled, Ordering::Relaxed);
+}
+
+/// Error for a response that doesn't have the expected shape
+fn unexpected_response(message: &str, resp_node: &Node) -> anyhow::Error {
+    if VERBOSE_ERRORS.load(Ordering::Relaxed) {
+        anyhow::anyhow!("{}; server response: {:?}", message, resp_node)
+    } else {
+        anyhow::anyhow!("{} (run with --verbose to see the response)", message)
+    }
+}
+
+/// Top-level error codes meaning the account itself is blocked from adding members
+const ACCOUNT_RESTRICTED_CODES: [u64; 2] = [401, 423];
+
+/// The automation account is restricted (banned/blocked) from adding participants.
+///
+/// Returned by `add_group_participants` when the server rejects the whole IQ
+/// instead of reporting per-participant results. Callers should abort the run.
+#[derive(Debug, Clone)]
+pub struct AccountRestricted {
+    pub code: u64,
+    pub text: String,
+}
+
+impl std::fmt::Display for AccountRestricted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "account restricted by WhatsApp (error code {}: {})",
+            self.code, self.text
+        )
+    }
+}
+
+impl std::error::Error for AccountRestricted {}
+
+/// The whole add request was rate limited ("adding too fast"), not a single member
+///
+/// Per-member rate limits are reported as a 429 participant result instead; this
+/// error means every further add will fail until the batch slows down.
+#[derive(Debug, Clone)]
+pub struct GlobalRateLimit {
+    pub text: String,
+}
+
+impl std::fmt::Display for GlobalRateLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rate limited by WhatsApp ({})", self.text)
+    }
+}
+
+impl std::error::Error for GlobalRateLimit {}
+
+/// The group rejected the add because of its membership TTL; nobody was added
+#[derive(Debug, Clone)]
+pub struct MemberTtlRejected {
+    pub text: String,
+}
+
+impl std::fmt::Display for MemberTtlRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the group rejected the membership TTL ({})", self.text)
+    }
+}
+
+impl std::error::Error for MemberTtlRejected {}
+
+/// Any other error code the server answered an IQ with
+#[derive(Debug, Clone)]
+struct ServerRejected {
+    code: u64,
+    text: String,
+}
+
+impl std::fmt::Display for ServerRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server returned error {}: {}", self.code, self.text)
+    }
+}
+
+impl std::error::Error for ServerRejected {}
+
+/// Detect a top-level `<error>` on an IQ response
+///
+/// Returns `AccountRestricted` for account-level codes, `GlobalRateLimit` for a
+/// request-wide 429 and `ServerRejected` otherwise.
+fn check_top_level_error(resp_node: &Node) -> Result<()> {
+    let Some(error_node) = resp_node.get_optional_child("error") else {
+        return Ok(());
+    };
+
+    let mut parser = wacore_binary::attrs::AttrParser::new(error_node);
+    let code = parser.optional_u64("code").unwrap_or(0);
+    let text = parser
+        .optional_string("text")
+        .unwrap_or("unknown")
+        .to_string();
+
+    if ACCOUNT_RESTRICTED_CODES.contains(&code) {
+        return Err(AccountRestricted { code, text }.into());
+    }
+    if code == 429 {
+        return Err(GlobalRateLimit { text }.into());
+    }
+
+    Err(ServerRejected { code, text }.into())
+}
+
+/// The code and text of an IQ the server answered with an error
+fn server_error(error: &whatsapp_rust::request::IqError) -> Option<(u64, &str)> {
+    match error {
+        whatsapp_rust::request::IqError::ServerError { code, text } => {
+            Some((u64::from(*code), text.as_str()))
+        }
+        _ => None,
+    }
+}
+
+/// Map an IQ send failure to `AccountRestricted`, `GlobalRateLimit` or `ServerRejected`
+///
+/// Only the code and text of a server error answer are looked at; other failures
+/// (timeouts, disconnects) are returned as is.
+fn classify_send_error(error: whatsapp_rust::request::IqError) -> anyhow::Error {
+    let Some((code, text)) = server_error(&error) else {
+        return error.into();
+    };
+
+    if ACCOUNT_RESTRICTED_CODES.contains(&code) {
+        return AccountRestricted {
+            code,
+            text: text.to_string(),
+        }
+        .into();
+    }
+    if code == 429 || text == "rate-overlimit" {
+        return GlobalRateLimit {
+            text: text.to_string(),
+        }
+        .into();
+    }
+
+    ServerRejected {
+        code,
+        text: text.to_string(),
+    }
+    .into()
+}
+
+/// Replace a 403 from a privileged IQ with an explicit not-authorized error
+///
+/// `role` names who may send the IQ, e.g. "a group admin". Only a `ServerRejected`
+/// error is looked at, so pass send failures through `classify_send_error` first.
+fn map_not_authorized(error: anyhow::Error, action: &str, role: &str) -> anyhow::Error {
+    let refused = error
+        .downcast_ref::<ServerRejected>()
+        .is_some_and(|rejection| rejection.code == 403 || rejection.text == "not-authorized");
+
+    if refused {
+        anyhow::anyhow!("Not authorized to {}: you must be {}", action, role)
+    } else {
+        error
+    }
+}
+
+/// Maximum number of participants WhatsApp allows in a regular group
+pub const MAX_GROUP_SIZE: usize = 1024;
+
+/// Most group metadata queries `query_many_group_metadata` keeps in flight at once
+pub const METADATA_QUERY_CONCURRENCY: usize = 4;
+
+/// A member of a group, with its admin role
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupParticipant {
+    pub jid: Jid,
+    pub is_admin: bool,
+    /// The group's super admin (its creator, unless ownership was transferred)
+    pub is_super_admin: bool,
+    /// Phone-number JID of an `@lid` participant, when the server or a lookup reports it
+    pub phone_number: Option<Jid>,
+}
+
+impl GroupParticipant {
+    /// Whether this participant is `user`, matched by user on its JID or its phone number
+    pub fn matches(&self, user: &Jid) -> bool {
+        self.jid.user == user.user
+            || self
+                .phone_number
+                .as_ref()
+                .is_some_and(|phone_number| phone_number.user == user.user)
+    }
+
+    /// An `@lid` participant whose phone number is unknown
+    pub fn is_unmapped_lid(&self) -> bool {
+        self.jid.server == "lid" && self.phone_number.is_none()
+    }
+}
+
 /// Group metadata including name and participants
 #[derive(Debug, Clone)]
 pub struct GroupMetadata {
@@ -11,6 +242,188 @@ pub struct GroupMetadata {
     pub jid: Jid,
     pub subject: String,
     pub participant_count: usize,
+    /// Whether the group is a community parent group
+    pub is_community: bool,
+    /// Sub-groups linked to a community parent (empty for regular groups)
+    pub linked_subgroups: Vec<Jid>,
+    /// Whether new members need an admin's approval to join
+    pub approval_required: bool,
+    /// Every participant with their role
+    pub participants: Vec<GroupParticipant>,
+    /// The group's creator, when the server reports it
+    pub owner: Option<Jid>,
+    /// Disappearing-messages timer (`None` when disabled)
+    pub ephemeral: Option<Duration>,
+}
+
+impl GroupMetadata {
+    /// Participants flagged admin or super admin
+    pub fn admins(&self) -> Vec<&Jid> {
+        self.participants
+            .iter()
+            .filter(|participant| participant.is_admin || participant.is_super_admin)
+            .map(|participant| &participant.jid)
+            .collect()
+    }
+
+    /// Whether `user` is a participant, matched by user so any device or server matches
+    ///
+    /// `@lid` participants also match their phone number when it is known.
+    pub fn has_participant(&self, user: &Jid) -> bool {
+        self.participants
+            .iter()
+            .any(|participant| participant.matches(user))
+    }
+
+    /// `@lid` participants that can't be matched to a phone number
+    pub fn unmapped_participants(&self) -> Vec<&Jid> {
+        self.participants
+            .iter()
+            .filter(|participant| participant.is_unmapped_lid())
+            .map(|participant| &participant.jid)
+            .collect()
+    }
+
+    /// Record the phone number of `@lid` participants found in `mapping` (phone, LID)
+    pub fn map_lids(&mut self, mapping: &[(Jid, Jid)]) {
+        for participant in &mut self.participants {
+            if !participant.is_unmapped_lid() {
+                continue;
+            }
+            if let Some((phone_jid, _)) = mapping
+                .iter()
+                .find(|(_, lid)| lid.user == participant.jid.user)
+            {
+                participant.phone_number = Some(phone_jid.clone());
+            }
+        }
+    }
+
+    /// Whether `own_jid` (the connected account) owns the group
+    pub fn owner_is_me(&self, own_jid: &Jid) -> bool {
+        self.owner
+            .as_ref()
+            .is_some_and(|owner| owner.user == own_jid.user)
+    }
+
+    /// Whether `own_jid` (the connected account) is an admin of the group
+    pub fn i_am_admin(&self, own_jid: &Jid) -> bool {
+        self.admins().iter().any(|admin| admin.user == own_jid.user)
+    }
+}
+
+/// A group invite link, identified by its invite code
+///
+/// Displays as the web URL (`https://chat.whatsapp.com/CODE`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InviteLink {
+    pub code: String,
+}
+
+impl InviteLink {
+    pub fn new(code: impl Into<String>) -> Self {
+        Self { code: code.into() }
+    }
+
+    /// The web URL, which works in browsers and in the app
+    pub fn url(&self) -> String {
+        format!("https://chat.whatsapp.com/{}", self.code)
+    }
+
+    /// The app deep link, which opens WhatsApp directly
+    pub fn deep_link(&self) -> String {
+        format!("whatsapp://chat?code={}", self.code)
+    }
+}
+
+impl std::fmt::Display for InviteLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.url())
+    }
+}
+
+/// Admins of the group that aren't in `keep`, matched by user
+///
+/// `@lid` admins match `keep` through their phone number or a LID in `keep`. Fails
+/// when an `@lid` admin matches neither and its phone number is unknown, since it
+/// may be a kept account.
+pub fn admins_to_demote(metadata: &GroupMetadata, keep: &[Jid]) -> Result<Vec<Jid>> {
+    let admins: Vec<&GroupParticipant> = metadata
+        .participants
+        .iter()
+        .filter(|participant| participant.is_admin || participant.is_super_admin)
+        .filter(|participant| !keep.iter().any(|kept| participant.matches(kept)))
+        .collect();
+
+    let unmapped: Vec<String> = admins
+        .iter()
+        .filter(|admin| admin.is_unmapped_lid())
+        .map(|admin| admin.jid.to_string())
+        .collect();
+    if !unmapped.is_empty() {
+        return Err(anyhow::anyhow!(
+            "can't tell whether LID admin(s) {} are in the keep list",
+            unmapped.join(", ")
+        ));
+    }
+
+    Ok(admins.into_iter().map(|admin| admin.jid.clone()).collect())
+}
+
+/// What it takes for a group to have exactly the `desired` members
+///
+/// `added` are desired members missing from the group, `removed` are participants
+/// not desired. Members are matched by user, and `@lid` participants by their phone
+/// number; check `unmapped_participants` before removing anyone.
+pub fn membership_plan(metadata: &GroupMetadata, desired: &[Jid]) -> ParticipantDiff {
+    ParticipantDiff {
+        added: desired
+            .iter()
+            .filter(|jid| !metadata.has_participant(jid))
+            .cloned()
+            .collect(),
+        removed: metadata
+            .participants
+            .iter()
+            .filter(|participant| !desired.iter().any(|jid| participant.matches(jid)))
+            .map(|participant| participant.jid.clone())
+            .collect(),
+    }
+}
+
+/// Who joined and who left between two metadata snapshots of a group
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ParticipantDiff {
+    pub added: Vec<Jid>,
+    pub removed: Vec<Jid>,
+}
+
+/// Compare the participants of two snapshots of the same group
+///
+/// Participants are matched by user, so a device or server change doesn't count
+/// as leaving and joining.
+pub fn diff_participants(before: &GroupMetadata, after: &GroupMetadata) -> ParticipantDiff {
+    let is_in = |metadata: &GroupMetadata, jid: &Jid| {
+        metadata
+            .participants
+            .iter()
+            .any(|participant| participant.jid.user == jid.user)
+    };
+
+    ParticipantDiff {
+        added: after
+            .participants
+            .iter()
+            .filter(|participant| !is_in(before, &participant.jid))
+            .map(|participant| participant.jid.clone())
+            .collect(),
+        removed: before
+            .participants
+            .iter()
+            .filter(|participant| !is_in(after, &participant.jid))
+            .map(|participant| participant.jid.clone())
+            .collect(),
+    }
 }
 
 /// Extension trait to add group management functionality to the WhatsApp Client
@@ -22,8 +435,50 @@ pub trait GroupManagement {
     /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
     ///
     /// # Returns
-    /// Result containing GroupMetadata with the group name and participant count
+    /// Result containing GroupMetadata with the group name and participant count.
+    /// The sub-groups of a community are left empty when they can't be listed.
     async fn query_group_metadata(&self, group_jid: &Jid) -> Result<GroupMetadata>;
+
+    /// Query the sub-groups linked to a community parent group
+    ///
+    /// # Arguments
+    /// * `community_jid` - The JID of the community parent group
+    ///
+    /// # Returns
+    /// Result containing the JIDs of the linked sub-groups
+    async fn query_linked_subgroups(&self, community_jid: &Jid) -> Result<Vec<Jid>>;
+
+    /// Check whether joining a group requires an admin's approval
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    ///
+    /// # Returns
+    /// Result containing true when membership approval mode is on. The same value is
+    /// available as `GroupMetadata::approval_required`.
+    async fn get_group_join_approval_mode(&self, group_jid: &Jid) -> Result<bool>;
+
+    /// Get the disappearing-messages timer of a group
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    ///
+    /// # Returns
+    /// Result containing the timer, or `None` when disappearing messages are off.
+    /// The same value is available as `GroupMetadata::ephemeral`.
+    async fn get_group_ephemeral(&self, group_jid: &Jid) -> Result<Option<Duration>>;
+
+    /// Turn membership approval mode on or off
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `required` - Whether new members need an admin's approval to join
+    ///
+    /// # Returns
+    /// Result indicating whether the mode was changed. Fails with a not-authorized
+    /// error when the account isn't an admin of the group.
+    async fn set_group_join_approval_mode(&self, group_jid: &Jid, required: bool) -> Result<()>;
+
     /// Add participants to a WhatsApp group
     ///
     /// # Arguments
@@ -31,7 +486,10 @@ pub trait GroupManagement {
     /// * `participant_jids` - List of participant JIDs to add (format: "1234567890@s.whatsapp.net")
     ///
     /// # Returns
-    /// Result containing a vector of tuples with (participant_jid, success: bool, error_code: Option<u64>)
+    /// Result containing a vector of tuples with (participant_jid, success: bool, error_code: Option<u64>).
+    /// Results follow the order of `participant_jids`, whatever order the server answers in.
+    /// Fails with `AccountRestricted` when the account itself is blocked from adding members.
+    /// Slices larger than `MAX_PARTICIPANTS_PER_IQ` are sent as several IQs.
     ///
     /// # Example
     /// ```no_run
@@ -58,6 +516,20 @@ pub trait GroupManagement {
         participant_jids: &[Jid],
     ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
 
+    /// Add participants whose membership expires after `member_ttl` (experimental)
+    ///
+    /// Same as `add_group_participants`, with an `expiration` attribute on every
+    /// participant. The attribute isn't documented by WhatsApp and may be ignored,
+    /// in which case the members stay for good. `member_ttl` must be one of
+    /// `MEMBER_TTLS`. When the server rejects the request with a 400, nobody is
+    /// added and `MemberTtlRejected` is returned.
+    async fn add_group_participants_with_ttl(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+        member_ttl: Duration,
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
     /// Remove participants from a WhatsApp group
     ///
     /// # Arguments
@@ -66,119 +538,845 @@ pub trait GroupManagement {
     ///
     /// # Returns
     /// Result containing a vector of tuples with (participant_jid, success: bool, error_code: Option<u64>)
-    #[allow(dead_code)]
     async fn remove_group_participants(
         &self,
         group_jid: &Jid,
         participant_jids: &[Jid],
     ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
 
+    /// Make participants of a WhatsApp group admins
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `participant_jids` - List of participant JIDs to promote (format: "1234567890@s.whatsapp.net")
+    ///
+    /// # Returns
+    /// Result containing a vector of tuples with (participant_jid, success: bool, error_code: Option<u64>).
+    /// Slices larger than `MAX_PARTICIPANTS_PER_IQ` are sent as several IQs.
+    async fn promote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Take admin rights away from participants of a WhatsApp group
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `participant_jids` - List of admin JIDs to demote (format: "1234567890@s.whatsapp.net")
+    ///
+    /// # Returns
+    /// Result containing a vector of tuples with (participant_jid, success: bool, error_code: Option<u64>).
+    /// Slices larger than `MAX_PARTICIPANTS_PER_IQ` are sent as several IQs.
+    async fn demote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Check whether a user is a participant of a WhatsApp group
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `user` - The JID of the user (format: "1234567890@s.whatsapp.net")
+    ///
+    /// # Returns
+    /// Result containing whether the user is in the group. Currently backed by a
+    /// full metadata query, plus a LID lookup when some participants are only
+    /// known by LID.
+    async fn is_participant(&self, group_jid: &Jid, user: &Jid) -> Result<bool>;
+
     /// Get the invite link for a WhatsApp group
     ///
     /// # Arguments
     /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
     ///
     /// # Returns
-    /// Result containing the invite link (format: "https://chat.whatsapp.com/XXXXXX")
+    /// Result containing the invite link, whose code also builds the app deep link
     #[allow(dead_code)]
-    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<String>;
-}
+    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<InviteLink>;
 
-impl GroupManagement for Client {
-    async fn query_group_metadata(&self, group_jid: &Jid) -> Result<GroupMetadata> {
-        let query_node = NodeBuilder::new("query")
-            .attr("request", "interactive")
-            .build();
+    /// Set the icon (profile picture) of a WhatsApp group
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `jpeg_bytes` - The new icon, a square JPEG between 192x192 and 640x640 pixels
+    ///
+    /// # Returns
+    /// Result indicating whether the icon was updated
+    async fn set_group_icon(&self, group_jid: &Jid, jpeg_bytes: &[u8]) -> Result<()>;
 
-        let iq = whatsapp_rust::request::InfoQuery {
-            namespace: "w:g2",
-            query_type: whatsapp_rust::request::InfoQueryType::Get,
-            to: group_jid.clone(),
-            content: Some(NodeContent::Nodes(vec![query_node])),
-            id: None,
-            target: None,
-            timeout: None,
-        };
+    /// Resolve the display name of a WhatsApp user through a usync query
+    ///
+    /// # Arguments
+    /// * `jid` - The JID of the user (format: "1234567890@s.whatsapp.net")
+    ///
+    /// # Returns
+    /// Result containing the user's push name when the server includes it, else
+    /// the verified name of a business account, or `None` when neither is exposed
+    async fn resolve_push_name(&self, jid: &Jid) -> Result<Option<String>>;
 
-        let resp_node = self.send_iq(iq).await?;
+    /// Look up the LID of a phone-number JID with a contacts (usync) query
+    ///
+    /// # Returns
+    /// Result containing the `@lid` JID, `None` when the server doesn't know one
+    async fn resolve_lid(&self, phone_jid: &Jid) -> Result<Option<Jid>>;
 
-        let group_node = resp_node
-            .get_optional_child("group")
-            .ok_or_else(|| anyhow::anyhow!("<group> not found in group info response"))?;
+    /// Check which phone-number JIDs are registered on WhatsApp with a contacts (usync) query
+    ///
+    /// # Arguments
+    /// * `jids` - Phone-number JIDs (format: "1234567890@s.whatsapp.net")
+    ///
+    /// # Returns
+    /// Result containing `(jid, registered)` for each JID, in the order of `jids`.
+    /// Only an explicit "not registered" answer counts as unregistered; JIDs the
+    /// server leaves out of its answer are reported as registered. Slices larger
+    /// than `USYNC_MAX_USERS` are sent as several queries.
+    async fn check_numbers_on_whatsapp(&self, jids: &[Jid]) -> Result<Vec<(Jid, bool)>>;
 
-        let mut parser = wacore_binary::attrs::AttrParser::new(group_node);
-        let subject = parser
-            .optional_string("subject")
-            .unwrap_or("Unknown Group")
-            .to_string();
+    /// List every group the account participates in
+    ///
+    /// # Returns
+    /// Result containing each group's JID and subject
+    async fn list_my_groups(&self) -> Result<Vec<(Jid, String)>>;
 
-        let participant_count = group_node.get_children_by_tag("participant").len();
+    /// Make another participant the owner of a group
+    ///
+    /// # Arguments
+    /// * `group_jid` - The JID of the group (format: "1234567890-1234567890@g.us")
+    /// * `new_owner` - The JID of the participant to hand the group to
+    ///
+    /// # Returns
+    /// Result indicating whether ownership was transferred. Fails when `new_owner`
+    /// isn't a participant of the group, or with a not-authorized error when the
+    /// account isn't the current owner.
+    async fn transfer_group_ownership(&self, group_jid: &Jid, new_owner: &Jid) -> Result<()>;
 
-        Ok(GroupMetadata {
-            jid: group_jid.clone(),
-            subject,
-            participant_count,
+    /// Look up the group an invite code points to, without joining it
+    ///
+    /// # Arguments
+    /// * `invite_code` - The code at the end of "https://chat.whatsapp.com/XXXXXX"
+    ///
+    /// # Returns
+    /// Result containing the JID of the group
+    async fn resolve_invite_code(&self, invite_code: &str) -> Result<Jid>;
+}
+
+/// Most users sent in one contacts (usync) query
+pub const USYNC_MAX_USERS: usize = 50;
+
+/// Maximum width/height (in pixels) accepted for group icons
+pub const MAX_GROUP_ICON_DIMENSION: u16 = 640;
+
+/// Minimum width/height (in pixels) accepted for group icons
+pub const MIN_GROUP_ICON_DIMENSION: u16 = 192;
+
+/// JID of the WhatsApp server itself, used as the recipient of non-group IQs
+fn server_jid() -> Jid {
+    Jid::new("", "s.whatsapp.net")
+}
+
+/// JID of the group server, used as the recipient of account-wide group IQs
+fn group_server_jid() -> Jid {
+    Jid::new("", "g.us")
+}
+
+/// Send one `<add>` IQ for the given participants
+async fn send_add_iq(
+    client: &Client,
+    group_jid: &Jid,
+    participant_jids: &[Jid],
+    member_ttl: Option<Duration>,
+) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+    // Build participant nodes, with the membership expiration (in seconds) if any
+    let participant_nodes: Vec<_> = participant_jids
+        .iter()
+        .map(|jid| {
+            let participant = NodeBuilder::new("participant").attr("jid", jid.to_string());
+            match member_ttl {
+                Some(ttl) => participant.attr("expiration", ttl.as_secs().to_string()),
+                None => participant,
+            }
+            .build()
         })
-    }
+        .collect();
 
-    async fn add_group_participants(
-        &self,
-        group_jid: &Jid,
-        participant_jids: &[Jid],
-    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
-        if participant_jids.is_empty() {
-            return Ok(vec![]);
-        }
+    // Build add node with participants
+    let add_node = NodeBuilder::new("add").children(participant_nodes).build();
 
-        // Build participant nodes
-        let participant_nodes: Vec<_> = participant_jids
-            .iter()
-            .map(|jid| {
-                NodeBuilder::new("participant")
-                    .attr("jid", jid.to_string())
-                    .build()
+    // Build the IQ query
+    let iq = whatsapp_rust::request::InfoQuery {
+        namespace: "w:g2",
+        query_type: whatsapp_rust::request::InfoQueryType::Set,
+        to: group_jid.clone(),
+        content: Some(NodeContent::Nodes(vec![add_node])),
+        id: None,
+        target: None,
+        timeout: Some(iq_timeout()),
+    };
+
+    // Send the IQ and get response
+    let resp_node = client.send_iq(iq).await.map_err(classify_send_error)?;
+
+    // An account-level rejection comes back as a top-level <error> instead of <add>
+    check_top_level_error(&resp_node)?;
+
+    if resp_node.get_optional_child("add").is_none() {
+        return Err(unexpected_response(
+            "<add> not found in add response",
+            &resp_node,
+        ));
+    }
+
+    // Parse the response to check for errors or success, in the order of the request
+    let results = in_request_order(
+        participant_jids,
+        parse_participant_results(&resp_node, "add"),
+    );
+
+    for (jid, success, error_code) in &results {
+        match (success, error_code) {
+            (false, Some(code)) => {
+                log::warn!("Failed to add participant {}: error code {}", jid, code)
+            }
+            _ => log::info!("Successfully added participant: {}", jid),
+        }
+    }
+
+    Ok(results)
+}
+
+/// Retry phone-number participants rejected with 400 under their LID, once
+///
+/// Only when enabled with `set_lid_fallback`. Results keep the phone-number JID,
+/// so callers can still match them to their request.
+async fn retry_rejected_as_lid(
+    client: &Client,
+    group_jid: &Jid,
+    results: &mut [(Jid, bool, Option<u64>)],
+    member_ttl: Option<Duration>,
+) {
+    if !LID_FALLBACK.load(Ordering::Relaxed) {
+        return;
+    }
+
+    for (jid, success, error_code) in results.iter_mut() {
+        if *error_code != Some(400) || jid.server != "s.whatsapp.net" {
+            continue;
+        }
+
+        let lid = match client.resolve_lid(jid).await {
+            Ok(Some(lid)) => lid,
+            Ok(None) => continue,
+            Err(e) => {
+                log::debug!("Could not look up the LID of {}: {}", jid, e);
+                continue;
+            }
+        };
+
+        log::info!(
+            "Add of {} rejected with 400, retrying by its LID {}",
+            jid,
+            lid
+        );
+        match send_add_iq(client, group_jid, std::slice::from_ref(&lid), member_ttl).await {
+            Ok(lid_results) => {
+                if let Some((_, lid_success, lid_error_code)) = lid_results.into_iter().next() {
+                    *success = lid_success;
+                    *error_code = lid_error_code;
+                }
+            }
+            Err(e) => log::warn!("Adding {} by its LID {} failed: {}", jid, lid, e),
+        }
+    }
+}
+
+/// Send one `<promote>` or `<demote>` IQ (`action`) for the given participants
+async fn send_role_iq(
+    client: &Client,
+    group_jid: &Jid,
+    participant_jids: &[Jid],
+    action: &str,
+) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+    let participant_nodes: Vec<_> = participant_jids
+        .iter()
+        .map(|jid| {
+            NodeBuilder::new("participant")
+                .attr("jid", jid.to_string())
+                .build()
+        })
+        .collect();
+
+    let role_node = NodeBuilder::new(action).children(participant_nodes).build();
+
+    let iq = whatsapp_rust::request::InfoQuery {
+        namespace: "w:g2",
+        query_type: whatsapp_rust::request::InfoQueryType::Set,
+        to: group_jid.clone(),
+        content: Some(NodeContent::Nodes(vec![role_node])),
+        id: None,
+        target: None,
+        timeout: Some(iq_timeout()),
+    };
+
+    let description = format!("{} participants", action);
+    let resp_node = client
+        .send_iq(iq)
+        .await
+        .map_err(|e| map_not_authorized(classify_send_error(e), &description, "a group admin"))?;
+    check_top_level_error(&resp_node)
+        .map_err(|e| map_not_authorized(e, &description, "a group admin"))?;
+
+    Ok(parse_participant_results(&resp_node, action))
+}
+
+/// Membership durations allowed for participants added with a TTL
+///
+/// The `expiration` attribute is experimental and undocumented; these are the
+/// durations the app offers for disappearing messages (24 hours, 7 days and 90
+/// days), assumed to apply to membership as well.
+pub const MEMBER_TTLS: [Duration; 3] = [
+    Duration::from_secs(24 * 3600),
+    Duration::from_secs(7 * 24 * 3600),
+    Duration::from_secs(90 * 24 * 3600),
+];
+
+/// Check a membership TTL against `MEMBER_TTLS`
+pub fn validate_member_ttl(ttl: Duration) -> Result<()> {
+    if MEMBER_TTLS.contains(&ttl) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "unsupported membership TTL of {}s (allowed: 24h, 168h or 2160h)",
+            ttl.as_secs()
+        ))
+    }
+}
+
+/// Whether an IQ failed with a top-level 400 (the server didn't understand the request)
+fn is_bad_request(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<ServerRejected>()
+        .is_some_and(|rejection| rejection.code == 400)
+}
+
+/// Largest number of participants sent in a single `<add>` IQ
+pub const MAX_PARTICIPANTS_PER_IQ: usize = 50;
+
+/// Send participants in chunks of at most `MAX_PARTICIPANTS_PER_IQ`, concatenating the results
+///
+/// Returns the error when the first chunk's IQ fails. Once a chunk went through,
+/// a failed IQ only marks its own members as failed without an error code, so
+/// members the server already added are still reported.
+async fn in_chunks<'a, F, Fut>(
+    participant_jids: &'a [Jid],
+    mut send_chunk: F,
+) -> Result<Vec<(Jid, bool, Option<u64>)>>
+where
+    F: FnMut(&'a [Jid]) -> Fut,
+    Fut: std::future::Future<Output = Result<Vec<(Jid, bool, Option<u64>)>>>,
+{
+    let mut results = Vec::with_capacity(participant_jids.len());
+    for chunk in participant_jids.chunks(MAX_PARTICIPANTS_PER_IQ) {
+        match send_chunk(chunk).await {
+            Ok(chunk_results) => results.extend(chunk_results),
+            Err(e) if results.is_empty() => return Err(e),
+            Err(e) => {
+                log::warn!("A chunk of {} participants failed: {}", chunk.len(), e);
+                results.extend(chunk.iter().map(|jid| (jid.clone(), false, None)));
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// Parse the `<participant>` children of a group info node
+fn parse_participants(group_node: &Node) -> Vec<GroupParticipant> {
+    group_node
+        .get_children_by_tag("participant")
+        .into_iter()
+        .filter_map(|participant| {
+            let mut parser = wacore_binary::attrs::AttrParser::new(participant);
+            let jid = parser.optional_string("jid")?.parse::<Jid>().ok()?;
+            let role = parser.optional_string("type");
+            // LID-addressed groups carry the phone number as a separate attribute
+            let phone_number = parser
+                .optional_string("phone_number")
+                .and_then(|phone_number| phone_number.parse::<Jid>().ok());
+
+            Some(GroupParticipant {
+                jid,
+                is_admin: role == Some("admin"),
+                is_super_admin: role == Some("superadmin"),
+                phone_number,
             })
-            .collect();
+        })
+        .collect()
+}
 
-        // Build add node with participants
-        let add_node = NodeBuilder::new("add").children(participant_nodes).build();
+/// Read the disappearing-messages timer from a group info node
+///
+/// The timer is carried as `<ephemeral expiration="seconds"/>`; absent or 0 means off.
+fn parse_ephemeral(group_node: &Node) -> Option<Duration> {
+    let ephemeral = group_node.get_optional_child("ephemeral")?;
+    let expiration = wacore_binary::attrs::AttrParser::new(ephemeral).optional_u64("expiration")?;
+
+    (expiration > 0).then(|| Duration::from_secs(expiration))
+}
+
+/// Read the membership approval mode from a group info node
+///
+/// The mode is carried as `<membership_approval_mode><group_join state="on"/></membership_approval_mode>`.
+fn parse_approval_required(group_node: &Node) -> bool {
+    group_node
+        .get_optional_child("membership_approval_mode")
+        .and_then(|mode| mode.get_optional_child("group_join"))
+        .is_some_and(|group_join| {
+            wacore_binary::attrs::AttrParser::new(group_join).optional_string("state") == Some("on")
+        })
+}
+
+/// Extract the name of the first user in a usync response
+///
+/// Prefers the push name (the user's `<notify>` result), then the verified name of
+/// a business account.
+fn parse_usync_name(resp_node: &Node) -> Option<String> {
+    use prost::Message;
+
+    let user = resp_node
+        .get_optional_child("usync")?
+        .get_optional_child("list")?
+        .get_optional_child("user")?;
+
+    if let Some(NodeContent::Bytes(push_name)) = user
+        .get_optional_child("notify")
+        .and_then(|notify| notify.content.as_ref())
+        && let Ok(push_name) = std::str::from_utf8(push_name)
+        && !push_name.trim().is_empty()
+    {
+        return Some(push_name.trim().to_string());
+    }
+
+    let verified_name = user
+        .get_optional_child("business")?
+        .get_optional_child("verified_name")?;
+
+    let Some(NodeContent::Bytes(certificate)) = &verified_name.content else {
+        return None;
+    };
+
+    let certificate = wa::VerifiedNameCertificate::decode(certificate.as_slice()).ok()?;
+    let details =
+        wa::verified_name_certificate::Details::decode(certificate.details?.as_slice()).ok()?;
+
+    details.verified_name.filter(|name| !name.is_empty())
+}
+
+/// Extract the LID of the first user in a usync response
+fn parse_usync_lid(resp_node: &Node) -> Option<Jid> {
+    let lid = resp_node
+        .get_optional_child("usync")?
+        .get_optional_child("list")?
+        .get_optional_child("user")?
+        .get_optional_child("lid")?;
+
+    wacore_binary::attrs::AttrParser::new(lid)
+        .optional_string("val")?
+        .parse()
+        .ok()
+}
+
+/// Read the users a usync contact query reported as not on WhatsApp
+fn parse_usync_unregistered(resp_node: &Node) -> Vec<Jid> {
+    let Some(list) = resp_node
+        .get_optional_child("usync")
+        .and_then(|usync| usync.get_optional_child("list"))
+    else {
+        return Vec::new();
+    };
+
+    list.get_children_by_tag("user")
+        .into_iter()
+        .filter(|user| {
+            user.get_optional_child("contact").is_some_and(|contact| {
+                wacore_binary::attrs::AttrParser::new(contact).optional_string("type")
+                    == Some("out")
+            })
+        })
+        .filter_map(|user| {
+            wacore_binary::attrs::AttrParser::new(user)
+                .optional_string("jid")?
+                .parse()
+                .ok()
+        })
+        .collect()
+}
+
+/// Read the (width, height) of a JPEG image from its start-of-frame marker
+fn jpeg_dimensions(jpeg_bytes: &[u8]) -> Option<(u16, u16)> {
+    let mut pos = 2;
+
+    while pos + 4 <= jpeg_bytes.len() {
+        if jpeg_bytes[pos] != 0xFF {
+            return None;
+        }
+
+        let marker = jpeg_bytes[pos + 1];
+        let segment_len = u16::from_be_bytes([jpeg_bytes[pos + 2], jpeg_bytes[pos + 3]]) as usize;
+
+        // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            // The whole frame header must be there, not just its dimensions
+            if segment_len < 7 || pos + 2 + segment_len > jpeg_bytes.len() {
+                return None;
+            }
+            let height = u16::from_be_bytes([jpeg_bytes[pos + 5], jpeg_bytes[pos + 6]]);
+            let width = u16::from_be_bytes([jpeg_bytes[pos + 7], jpeg_bytes[pos + 8]]);
+            return Some((width, height));
+        }
+
+        pos += 2 + segment_len;
+    }
+
+    None
+}
+
+/// Check that an image is a JPEG within WhatsApp's group icon constraints
+fn validate_group_icon(jpeg_bytes: &[u8]) -> Result<()> {
+    if !jpeg_bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        anyhow::bail!("Group icon must be a JPEG image");
+    }
+
+    let (width, height) = jpeg_dimensions(jpeg_bytes)
+        .ok_or_else(|| anyhow::anyhow!("Could not read the dimensions of the JPEG image"))?;
+
+    if width > MAX_GROUP_ICON_DIMENSION || height > MAX_GROUP_ICON_DIMENSION {
+        anyhow::bail!(
+            "Group icon is {}x{} but WhatsApp accepts at most {}x{}; please resize it first",
+            width,
+            height,
+            MAX_GROUP_ICON_DIMENSION,
+            MAX_GROUP_ICON_DIMENSION
+        );
+    }
+    if width < MIN_GROUP_ICON_DIMENSION || height < MIN_GROUP_ICON_DIMENSION {
+        anyhow::bail!(
+            "Group icon is {}x{} but WhatsApp needs at least {}x{}",
+            width,
+            height,
+            MIN_GROUP_ICON_DIMENSION,
+            MIN_GROUP_ICON_DIMENSION
+        );
+    }
+    if width != height {
+        anyhow::bail!(
+            "Group icon is {}x{} but must be square; please crop it first",
+            width,
+            height
+        );
+    }
+
+    Ok(())
+}
+
+/// Parse the per-participant results of an add/remove IQ response
+///
+/// # Arguments
+/// * `resp_node` - The IQ response node returned by the server
+/// * `action` - The wrapper tag of the response (`"add"` or `"remove"`)
+///
+/// # Returns
+/// A vector of tuples with (participant_jid, success: bool, error_code: Option<u64>).
+/// Returns an empty vector when the `<action>` wrapper is missing.
+fn parse_participant_results(resp_node: &Node, action: &str) -> Vec<(Jid, bool, Option<u64>)> {
+    let mut results = Vec::new();
+
+    if let Some(action_response) = resp_node.get_optional_child(action) {
+        for participant_node in action_response.get_children_by_tag("participant") {
+            let mut parser = wacore_binary::attrs::AttrParser::new(participant_node);
+            let jid = parser.jid("jid");
+            let error_code = parser.optional_u64("error");
+
+            match error_code {
+                Some(code) => results.push((jid, false, Some(code))),
+                None => results.push((jid, true, None)),
+            }
+        }
+    }
+
+    results
+}
+
+/// Reorder participant results to follow `participant_jids`
+///
+/// The server echoes `<participant>` nodes in no particular order. Results are
+/// matched to the request by user; any result matching no requested JID is kept
+/// at the end, in server order.
+fn in_request_order(
+    participant_jids: &[Jid],
+    mut results: Vec<(Jid, bool, Option<u64>)>,
+) -> Vec<(Jid, bool, Option<u64>)> {
+    let mut ordered = Vec::with_capacity(results.len());
+    for requested in participant_jids {
+        if let Some(index) = results
+            .iter()
+            .position(|(jid, _, _)| jid.user == requested.user)
+        {
+            ordered.push(results.remove(index));
+        }
+    }
+    ordered.extend(results);
+    ordered
+}
+
+/// Parse the sub-group JIDs from a community `<sub_groups>` response
+fn parse_linked_subgroups(resp_node: &Node) -> Vec<Jid> {
+    let Some(sub_groups) = resp_node.get_optional_child("sub_groups") else {
+        return Vec::new();
+    };
+
+    sub_groups
+        .get_children_by_tag("group")
+        .into_iter()
+        .filter_map(|group_node| {
+            let mut parser = wacore_binary::attrs::AttrParser::new(group_node);
+            let id = parser.optional_string("id")?;
+            format!("{}@g.us", id).parse().ok()
+        })
+        .collect()
+}
+
+/// Build the `<promote>` node making `new_owner` the group's super admin
+fn ownership_transfer_node(new_owner: &Jid) -> Node {
+    let participant_node = NodeBuilder::new("participant")
+        .attr("jid", new_owner.to_string())
+        .attr("type", "superadmin")
+        .build();
+
+    NodeBuilder::new("promote")
+        .children(vec![participant_node])
+        .build()
+}
+
+/// Parse `(jid, subject)` pairs from a `<groups>` participating response
+fn parse_participating_groups(resp_node: &Node) -> Vec<(Jid, String)> {
+    let Some(groups) = resp_node.get_optional_child("groups") else {
+        return Vec::new();
+    };
+
+    groups
+        .get_children_by_tag("group")
+        .into_iter()
+        .filter_map(|group_node| {
+            let mut parser = wacore_binary::attrs::AttrParser::new(group_node);
+            let id = parser.optional_string("id")?;
+            let jid = if id.contains('@') {
+                id.parse().ok()?
+            } else {
+                format!("{}@g.us", id).parse().ok()?
+            };
+            let subject = parser
+                .optional_string("subject")
+                .unwrap_or("Unknown Group")
+                .to_string();
+            Some((jid, subject))
+        })
+        .collect()
+}
+
+/// Parse the group JID from an invite-code `<group>` response
+fn parse_invite_group(resp_node: &Node) -> Option<Jid> {
+    let group_node = resp_node.get_optional_child("group")?;
+    let id = wacore_binary::attrs::AttrParser::new(group_node).optional_string("id")?;
+
+    if id.contains('@') {
+        id.parse().ok()
+    } else {
+        format!("{}@g.us", id).parse().ok()
+    }
+}
+
+impl GroupManagement for Client {
+    async fn query_group_metadata(&self, group_jid: &Jid) -> Result<GroupMetadata> {
+        let query_node = NodeBuilder::new("query")
+            .attr("request", "interactive")
+            .build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:g2",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: group_jid.clone(),
+            content: Some(NodeContent::Nodes(vec![query_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let resp_node = self.send_iq(iq).await?;
+
+        let group_node = resp_node.get_optional_child("group").ok_or_else(|| {
+            unexpected_response("<group> not found in group info response", &resp_node)
+        })?;
+
+        let mut parser = wacore_binary::attrs::AttrParser::new(group_node);
+        let subject = parser
+            .optional_string("subject")
+            .unwrap_or("Unknown Group")
+            .to_string();
+
+        let owner = parser
+            .optional_string("creator")
+            .and_then(|creator| creator.parse::<Jid>().ok());
+
+        let participants = parse_participants(group_node);
+        let participant_count = participants.len();
+        let approval_required = parse_approval_required(group_node);
+        let ephemeral = parse_ephemeral(group_node);
+
+        // Community parents carry a <parent> marker; their sub-groups need a separate
+        // query, which is best-effort so it can't fail the metadata lookup
+        let is_community = group_node.get_optional_child("parent").is_some();
+        let linked_subgroups = if is_community {
+            self.query_linked_subgroups(group_jid)
+                .await
+                .unwrap_or_else(|e| {
+                    log::warn!("Could not list the sub-groups of {}: {}", group_jid, e);
+                    Vec::new()
+                })
+        } else {
+            Vec::new()
+        };
+
+        Ok(GroupMetadata {
+            jid: group_jid.clone(),
+            subject,
+            participant_count,
+            is_community,
+            linked_subgroups,
+            approval_required,
+            participants,
+            owner,
+            ephemeral,
+        })
+    }
+
+    async fn get_group_join_approval_mode(&self, group_jid: &Jid) -> Result<bool> {
+        Ok(self
+            .query_group_metadata(group_jid)
+            .await?
+            .approval_required)
+    }
+
+    async fn get_group_ephemeral(&self, group_jid: &Jid) -> Result<Option<Duration>> {
+        Ok(self.query_group_metadata(group_jid).await?.ephemeral)
+    }
+
+    async fn set_group_join_approval_mode(&self, group_jid: &Jid, required: bool) -> Result<()> {
+        let group_join_node = NodeBuilder::new("group_join")
+            .attr("state", if required { "on" } else { "off" })
+            .build();
+        let mode_node = NodeBuilder::new("membership_approval_mode")
+            .children(vec![group_join_node])
+            .build();
 
-        // Build the IQ query
         let iq = whatsapp_rust::request::InfoQuery {
             namespace: "w:g2",
             query_type: whatsapp_rust::request::InfoQueryType::Set,
             to: group_jid.clone(),
-            content: Some(NodeContent::Nodes(vec![add_node])),
+            content: Some(NodeContent::Nodes(vec![mode_node])),
             id: None,
             target: None,
-            timeout: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let action = "change the membership approval mode";
+        let resp_node = self
+            .send_iq(iq)
+            .await
+            .map_err(|e| map_not_authorized(classify_send_error(e), action, "a group admin"))?;
+        check_top_level_error(&resp_node)
+            .map_err(|e| map_not_authorized(e, action, "a group admin"))?;
+
+        Ok(())
+    }
+
+    async fn query_linked_subgroups(&self, community_jid: &Jid) -> Result<Vec<Jid>> {
+        let sub_groups_node = NodeBuilder::new("sub_groups").build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:g2",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: community_jid.clone(),
+            content: Some(NodeContent::Nodes(vec![sub_groups_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
         };
 
-        // Send the IQ and get response
         let resp_node = self.send_iq(iq).await?;
 
-        // Parse the response to check for errors or success
-        let mut results = Vec::new();
-
-        if let Some(add_response) = resp_node.get_optional_child("add") {
-            for participant_node in add_response.get_children_by_tag("participant") {
-                let mut parser = wacore_binary::attrs::AttrParser::new(participant_node);
-                let jid = parser.jid("jid");
-                let error_code = parser.optional_u64("error");
-
-                if let Some(code) = error_code {
-                    log::warn!("Failed to add participant {}: error code {}", jid, code);
-                    results.push((jid, false, Some(code)));
-                } else {
-                    log::info!("Successfully added participant: {}", jid);
-                    results.push((jid, true, None));
+        Ok(parse_linked_subgroups(&resp_node))
+    }
+
+    async fn add_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        let mut results = in_chunks(participant_jids, |chunk| {
+            send_add_iq(self, group_jid, chunk, None)
+        })
+        .await?;
+
+        retry_rejected_as_lid(self, group_jid, &mut results, None).await;
+        Ok(results)
+    }
+
+    async fn add_group_participants_with_ttl(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+        member_ttl: Duration,
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        validate_member_ttl(member_ttl)?;
+
+        let mut results = in_chunks(participant_jids, |chunk| async move {
+            match send_add_iq(self, group_jid, chunk, Some(member_ttl)).await {
+                Err(e) if is_bad_request(&e) => Err(MemberTtlRejected {
+                    text: e.to_string(),
                 }
+                .into()),
+                result => result,
             }
-        }
+        })
+        .await?;
 
+        retry_rejected_as_lid(self, group_jid, &mut results, Some(member_ttl)).await;
         Ok(results)
     }
 
+    async fn promote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        in_chunks(participant_jids, |chunk| {
+            send_role_iq(self, group_jid, chunk, "promote")
+        })
+        .await
+    }
+
+    async fn demote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        in_chunks(participant_jids, |chunk| {
+            send_role_iq(self, group_jid, chunk, "demote")
+        })
+        .await
+    }
+
     async fn remove_group_participants(
         &self,
         group_jid: &Jid,
@@ -211,35 +1409,44 @@ impl GroupManagement for Client {
             content: Some(NodeContent::Nodes(vec![remove_node])),
             id: None,
             target: None,
-            timeout: None,
+            timeout: Some(iq_timeout()),
         };
 
         // Send the IQ and get response
         let resp_node = self.send_iq(iq).await?;
 
         // Parse the response to check for errors or success
-        let mut results = Vec::new();
-
-        if let Some(remove_response) = resp_node.get_optional_child("remove") {
-            for participant_node in remove_response.get_children_by_tag("participant") {
-                let mut parser = wacore_binary::attrs::AttrParser::new(participant_node);
-                let jid = parser.jid("jid");
-                let error_code = parser.optional_u64("error");
-
-                if let Some(code) = error_code {
-                    log::warn!("Failed to remove participant {}: error code {}", jid, code);
-                    results.push((jid, false, Some(code)));
-                } else {
-                    log::info!("Successfully removed participant: {}", jid);
-                    results.push((jid, true, None));
+        let results = parse_participant_results(&resp_node, "remove");
+
+        for (jid, success, error_code) in &results {
+            match (success, error_code) {
+                (false, Some(code)) => {
+                    log::warn!("Failed to remove participant {}: error code {}", jid, code)
                 }
+                _ => log::info!("Successfully removed participant: {}", jid),
             }
         }
 
         Ok(results)
     }
 
-    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<String> {
+    async fn is_participant(&self, group_jid: &Jid, user: &Jid) -> Result<bool> {
+        let metadata = self.query_group_metadata(group_jid).await?;
+        if metadata.has_participant(user) {
+            return Ok(true);
+        }
+
+        // LID-addressed groups may list the member under its LID without a phone number
+        if user.server == "s.whatsapp.net"
+            && !metadata.unmapped_participants().is_empty()
+            && let Some(lid) = self.resolve_lid(user).await?
+        {
+            return Ok(metadata.has_participant(&lid));
+        }
+        Ok(false)
+    }
+
+    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<InviteLink> {
         let invite_node = NodeBuilder::new("invite").build();
 
         let iq = whatsapp_rust::request::InfoQuery {
@@ -249,20 +1456,1111 @@ impl GroupManagement for Client {
             content: Some(NodeContent::Nodes(vec![invite_node])),
             id: None,
             target: None,
-            timeout: None,
+            timeout: Some(iq_timeout()),
         };
 
         let resp_node = self.send_iq(iq).await?;
 
         let invite_response = resp_node
             .get_optional_child("invite")
-            .ok_or_else(|| anyhow::anyhow!("<invite> not found in response"))?;
+            .ok_or_else(|| unexpected_response("<invite> not found in response", &resp_node))?;
 
         let mut parser = wacore_binary::attrs::AttrParser::new(invite_response);
         let invite_code = parser
             .optional_string("code")
-            .ok_or_else(|| anyhow::anyhow!("Invite code not found"))?;
+            .ok_or_else(|| unexpected_response("Invite code not found", &resp_node))?;
+
+        Ok(InviteLink::new(invite_code))
+    }
+
+    async fn set_group_icon(&self, group_jid: &Jid, jpeg_bytes: &[u8]) -> Result<()> {
+        validate_group_icon(jpeg_bytes)?;
+
+        let picture_node = NodeBuilder::new("picture")
+            .attr("type", "image")
+            .bytes(jpeg_bytes.to_vec())
+            .build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:profile:picture",
+            query_type: whatsapp_rust::request::InfoQueryType::Set,
+            to: server_jid(),
+            content: Some(NodeContent::Nodes(vec![picture_node])),
+            id: None,
+            target: Some(group_jid.clone()),
+            timeout: Some(iq_timeout()),
+        };
+
+        self.send_iq(iq).await?;
+
+        Ok(())
+    }
+
+    async fn resolve_push_name(&self, jid: &Jid) -> Result<Option<String>> {
+        let sid = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .to_string();
+
+        let query_node = NodeBuilder::new("query")
+            .children(vec![
+                NodeBuilder::new("notify").build(),
+                NodeBuilder::new("business")
+                    .children(vec![NodeBuilder::new("verified_name").build()])
+                    .build(),
+            ])
+            .build();
+        let list_node = NodeBuilder::new("list")
+            .children(vec![
+                NodeBuilder::new("user")
+                    .attr("jid", jid.to_string())
+                    .build(),
+            ])
+            .build();
+        let usync_node = NodeBuilder::new("usync")
+            .attr("sid", sid)
+            .attr("mode", "query")
+            .attr("last", "true")
+            .attr("index", "0")
+            .attr("context", "interactive")
+            .children(vec![query_node, list_node])
+            .build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "usync",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: server_jid(),
+            content: Some(NodeContent::Nodes(vec![usync_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let resp_node = self.send_iq(iq).await?;
+
+        Ok(parse_usync_name(&resp_node))
+    }
+
+    async fn resolve_lid(&self, phone_jid: &Jid) -> Result<Option<Jid>> {
+        let sid = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .to_string();
+
+        let query_node = NodeBuilder::new("query")
+            .children(vec![NodeBuilder::new("lid").build()])
+            .build();
+        let list_node = NodeBuilder::new("list")
+            .children(vec![
+                NodeBuilder::new("user")
+                    .attr("jid", phone_jid.to_string())
+                    .build(),
+            ])
+            .build();
+        let usync_node = NodeBuilder::new("usync")
+            .attr("sid", sid)
+            .attr("mode", "query")
+            .attr("last", "true")
+            .attr("index", "0")
+            .attr("context", "interactive")
+            .children(vec![query_node, list_node])
+            .build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "usync",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: server_jid(),
+            content: Some(NodeContent::Nodes(vec![usync_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let resp_node = self.send_iq(iq).await?;
+
+        Ok(parse_usync_lid(&resp_node))
+    }
+
+    async fn check_numbers_on_whatsapp(&self, jids: &[Jid]) -> Result<Vec<(Jid, bool)>> {
+        let mut unregistered = Vec::new();
+
+        for chunk in jids.chunks(USYNC_MAX_USERS) {
+            let sid = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis()
+                .to_string();
+
+            let query_node = NodeBuilder::new("query")
+                .children(vec![NodeBuilder::new("contact").build()])
+                .build();
+            let user_nodes: Vec<_> = chunk
+                .iter()
+                .map(|jid| {
+                    NodeBuilder::new("user")
+                        .children(vec![
+                            NodeBuilder::new("contact")
+                                .bytes(format!("+{}", jid.user).into_bytes())
+                                .build(),
+                        ])
+                        .build()
+                })
+                .collect();
+            let list_node = NodeBuilder::new("list").children(user_nodes).build();
+            let usync_node = NodeBuilder::new("usync")
+                .attr("sid", sid)
+                .attr("mode", "query")
+                .attr("last", "true")
+                .attr("index", "0")
+                .attr("context", "interactive")
+                .children(vec![query_node, list_node])
+                .build();
+
+            let iq = whatsapp_rust::request::InfoQuery {
+                namespace: "usync",
+                query_type: whatsapp_rust::request::InfoQueryType::Get,
+                to: server_jid(),
+                content: Some(NodeContent::Nodes(vec![usync_node])),
+                id: None,
+                target: None,
+                timeout: Some(iq_timeout()),
+            };
+
+            let resp_node = self.send_iq(iq).await?;
+            unregistered.extend(parse_usync_unregistered(&resp_node));
+        }
+
+        Ok(jids
+            .iter()
+            .map(|jid| {
+                let registered = !unregistered
+                    .iter()
+                    .any(|other| other.user == jid.user && other.server == jid.server);
+                (jid.clone(), registered)
+            })
+            .collect())
+    }
+
+    async fn list_my_groups(&self) -> Result<Vec<(Jid, String)>> {
+        let participating_node = NodeBuilder::new("participating").build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:g2",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: group_server_jid(),
+            content: Some(NodeContent::Nodes(vec![participating_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let resp_node = self.send_iq(iq).await?;
+
+        Ok(parse_participating_groups(&resp_node))
+    }
+
+    async fn transfer_group_ownership(&self, group_jid: &Jid, new_owner: &Jid) -> Result<()> {
+        let metadata = self.query_group_metadata(group_jid).await?;
+        if !metadata.has_participant(new_owner) {
+            anyhow::bail!(
+                "{} is not a participant of \"{}\"; add them before transferring ownership",
+                new_owner,
+                metadata.subject
+            );
+        }
+
+        let promote_node = ownership_transfer_node(new_owner);
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:g2",
+            query_type: whatsapp_rust::request::InfoQueryType::Set,
+            to: group_jid.clone(),
+            content: Some(NodeContent::Nodes(vec![promote_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let action = "transfer group ownership";
+        let resp_node = self
+            .send_iq(iq)
+            .await
+            .map_err(|e| map_not_authorized(classify_send_error(e), action, "the group owner"))?;
+        check_top_level_error(&resp_node)
+            .map_err(|e| map_not_authorized(e, action, "the group owner"))?;
+
+        match parse_participant_results(&resp_node, "promote").first() {
+            Some((_, false, Some(403))) => Err(anyhow::anyhow!(
+                "Not authorized to {}: you must be the group owner",
+                action
+            )),
+            Some((jid, false, code)) => Err(anyhow::anyhow!(
+                "Failed to make {} the owner (error code: {:?})",
+                jid,
+                code
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    async fn resolve_invite_code(&self, invite_code: &str) -> Result<Jid> {
+        let invite_node = NodeBuilder::new("invite").attr("code", invite_code).build();
+
+        let iq = whatsapp_rust::request::InfoQuery {
+            namespace: "w:g2",
+            query_type: whatsapp_rust::request::InfoQueryType::Get,
+            to: group_server_jid(),
+            content: Some(NodeContent::Nodes(vec![invite_node])),
+            id: None,
+            target: None,
+            timeout: Some(iq_timeout()),
+        };
+
+        let resp_node = self.send_iq(iq).await?;
+
+        parse_invite_group(&resp_node).ok_or_else(|| {
+            unexpected_response("<group> not found in invite info response", &resp_node)
+        })
+    }
+}
+
+/// The client operations the member utilities rely on
+///
+/// `Client` implements it on top of `GroupManagement` and its own messaging and
+/// media APIs, so batches can be driven by a scripted mock in tests.
+#[allow(async_fn_in_trait)]
+pub trait GroupOps {
+    /// Add participants, returning `(participant_jid, success, error_code)` per participant
+    async fn add_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Add participants whose membership expires after `member_ttl`
+    async fn add_group_participants_with_ttl(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+        member_ttl: Duration,
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Remove participants, returning `(participant_jid, success, error_code)` per participant
+    async fn remove_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Make participants admins, returning `(participant_jid, success, error_code)` per participant
+    async fn promote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Remove admin rights, returning `(participant_jid, success, error_code)` per participant
+    async fn demote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>>;
+
+    /// Query group metadata (subject, participants, settings)
+    async fn query_group_metadata(&self, group_jid: &Jid) -> Result<GroupMetadata>;
+
+    /// Query the metadata of several groups concurrently
+    ///
+    /// Returns one `(group_jid, result)` pair per group, in the order of `group_jids`;
+    /// a group that fails to load only fails its own entry. At most
+    /// `METADATA_QUERY_CONCURRENCY` queries are in flight at once.
+    async fn query_many_group_metadata(
+        &self,
+        group_jids: &[Jid],
+    ) -> Vec<(Jid, Result<GroupMetadata>)> {
+        let permits = tokio::sync::Semaphore::new(METADATA_QUERY_CONCURRENCY);
+
+        futures_util::future::join_all(group_jids.iter().map(|group_jid| {
+            let permits = &permits;
+            async move {
+                let _permit = permits.acquire().await;
+                (
+                    group_jid.clone(),
+                    self.query_group_metadata(group_jid).await,
+                )
+            }
+        }))
+        .await
+    }
+
+    /// Whether a user is a participant of a group
+    async fn is_participant(&self, group_jid: &Jid, user: &Jid) -> Result<bool>;
+
+    /// Get the invite link of a group
+    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<InviteLink>;
+
+    /// Resolve the display name of a user, `None` when unavailable
+    async fn resolve_push_name(&self, jid: &Jid) -> Result<Option<String>>;
+
+    /// Resolve the LID of a phone-number JID, `None` when the server has none
+    async fn resolve_lid(&self, phone_jid: &Jid) -> Result<Option<Jid>>;
+
+    /// Send a message to a user
+    async fn send_message(&self, to: &Jid, message: wa::Message) -> Result<()>;
+
+    /// Upload an image, returning an image message (without caption) pointing at it
+    async fn upload_image(&self, data: Vec<u8>) -> Result<wa::message::ImageMessage>;
+
+    /// Check which phone-number JIDs are registered, returning `(jid, registered)` per JID
+    async fn check_numbers_on_whatsapp(&self, jids: &[Jid]) -> Result<Vec<(Jid, bool)>>;
+
+    /// JIDs of the connected account (phone number and LID), empty before pairing
+    async fn own_jids(&self) -> Vec<Jid>;
+
+    /// Whether the websocket to WhatsApp is currently connected
+    async fn is_connected(&self) -> bool;
+}
+
+impl GroupOps for Client {
+    async fn add_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        GroupManagement::add_group_participants(self, group_jid, participant_jids).await
+    }
+
+    async fn add_group_participants_with_ttl(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+        member_ttl: Duration,
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        GroupManagement::add_group_participants_with_ttl(
+            self,
+            group_jid,
+            participant_jids,
+            member_ttl,
+        )
+        .await
+    }
+
+    async fn remove_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        GroupManagement::remove_group_participants(self, group_jid, participant_jids).await
+    }
+
+    async fn promote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        GroupManagement::promote_group_participants(self, group_jid, participant_jids).await
+    }
+
+    async fn demote_group_participants(
+        &self,
+        group_jid: &Jid,
+        participant_jids: &[Jid],
+    ) -> Result<Vec<(Jid, bool, Option<u64>)>> {
+        GroupManagement::demote_group_participants(self, group_jid, participant_jids).await
+    }
+
+    async fn query_group_metadata(&self, group_jid: &Jid) -> Result<GroupMetadata> {
+        GroupManagement::query_group_metadata(self, group_jid).await
+    }
+
+    async fn is_participant(&self, group_jid: &Jid, user: &Jid) -> Result<bool> {
+        GroupManagement::is_participant(self, group_jid, user).await
+    }
+
+    async fn get_group_invite_link(&self, group_jid: &Jid) -> Result<InviteLink> {
+        GroupManagement::get_group_invite_link(self, group_jid).await
+    }
+
+    async fn resolve_push_name(&self, jid: &Jid) -> Result<Option<String>> {
+        GroupManagement::resolve_push_name(self, jid).await
+    }
+
+    async fn resolve_lid(&self, phone_jid: &Jid) -> Result<Option<Jid>> {
+        GroupManagement::resolve_lid(self, phone_jid).await
+    }
+
+    async fn check_numbers_on_whatsapp(&self, jids: &[Jid]) -> Result<Vec<(Jid, bool)>> {
+        GroupManagement::check_numbers_on_whatsapp(self, jids).await
+    }
+
+    async fn own_jids(&self) -> Vec<Jid> {
+        [self.get_pn().await, self.get_lid().await]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    async fn is_connected(&self) -> bool {
+        Client::is_connected(self)
+    }
+
+    async fn send_message(&self, to: &Jid, message: wa::Message) -> Result<()> {
+        Client::send_message(self, to.clone(), message)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(())
+    }
+
+    async fn upload_image(&self, data: Vec<u8>) -> Result<wa::message::ImageMessage> {
+        let upload = self
+            .upload(data, wacore::download::MediaType::Image)
+            .await
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+
+        Ok(wa::message::ImageMessage {
+            url: Some(upload.url),
+            direct_path: Some(upload.direct_path),
+            media_key: Some(upload.media_key),
+            file_enc_sha256: Some(upload.file_enc_sha256),
+            file_sha256: Some(upload.file_sha256),
+            file_length: Some(upload.file_length),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(jid: &str, error: Option<&str>) -> Node {
+        let builder = NodeBuilder::new("participant").attr("jid", jid);
+        match error {
+            Some(code) => builder.attr("error", code).build(),
+            None => builder.build(),
+        }
+    }
+
+    fn iq_response(children: Vec<Node>) -> Node {
+        NodeBuilder::new("iq")
+            .attr("type", "result")
+            .children(children)
+            .build()
+    }
+
+    fn jid(s: &str) -> Jid {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_single_success_without_error_attr() {
+        let add = NodeBuilder::new("add")
+            .children(vec![participant("1234567890@s.whatsapp.net", None)])
+            .build();
+        let resp = iq_response(vec![add]);
+
+        let results = parse_participant_results(&resp, "add");
+
+        assert_eq!(results, vec![(jid("1234567890@s.whatsapp.net"), true, None)]);
+    }
+
+    #[test]
+    fn parses_mixed_success_and_conflict() {
+        let add = NodeBuilder::new("add")
+            .children(vec![
+                participant("1234567890@s.whatsapp.net", None),
+                participant("0987654321@s.whatsapp.net", Some("409")),
+            ])
+            .build();
+        let resp = iq_response(vec![add]);
+
+        let results = parse_participant_results(&resp, "add");
+
+        assert_eq!(
+            results,
+            vec![
+                (jid("1234567890@s.whatsapp.net"), true, None),
+                (jid("0987654321@s.whatsapp.net"), false, Some(409)),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_add_wrapper_yields_no_results() {
+        let resp = iq_response(vec![participant("1234567890@s.whatsapp.net", None)]);
+
+        let results = parse_participant_results(&resp, "add");
+
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn reorders_results_to_match_the_request() {
+        let requested = vec![
+            jid("111@s.whatsapp.net"),
+            jid("222@s.whatsapp.net"),
+            jid("333@s.whatsapp.net"),
+        ];
+        let add = NodeBuilder::new("add")
+            .children(vec![
+                participant("444@s.whatsapp.net", Some("400")),
+                participant("333@s.whatsapp.net", Some("403")),
+                participant("222@s.whatsapp.net", None),
+                participant("111@s.whatsapp.net", Some("409")),
+            ])
+            .build();
+        let resp = iq_response(vec![add]);
+
+        let results = in_request_order(&requested, parse_participant_results(&resp, "add"));
+
+        assert_eq!(
+            results,
+            vec![
+                (jid("111@s.whatsapp.net"), false, Some(409)),
+                (jid("222@s.whatsapp.net"), true, None),
+                (jid("333@s.whatsapp.net"), false, Some(403)),
+                (jid("444@s.whatsapp.net"), false, Some(400)),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_remove_wrapper() {
+        let remove = NodeBuilder::new("remove")
+            .children(vec![participant("1234567890@s.whatsapp.net", Some("404"))])
+            .build();
+        let resp = iq_response(vec![remove]);
+
+        assert!(parse_participant_results(&resp, "add").is_empty());
+        assert_eq!(
+            parse_participant_results(&resp, "remove"),
+            vec![(jid("1234567890@s.whatsapp.net"), false, Some(404))]
+        );
+    }
+
+    #[test]
+    fn parses_membership_approval_mode() {
+        let group = |children: Vec<Node>| NodeBuilder::new("group").children(children).build();
+        let approval = |state: &str| {
+            NodeBuilder::new("membership_approval_mode")
+                .children(vec![
+                    NodeBuilder::new("group_join").attr("state", state).build(),
+                ])
+                .build()
+        };
+
+        assert!(parse_approval_required(&group(vec![approval("on")])));
+        assert!(!parse_approval_required(&group(vec![approval("off")])));
+        assert!(!parse_approval_required(&group(vec![])));
+    }
+
+    #[test]
+    fn parses_participant_roles() {
+        let group = NodeBuilder::new("group")
+            .children(vec![
+                NodeBuilder::new("participant")
+                    .attr("jid", "111@s.whatsapp.net")
+                    .attr("type", "superadmin")
+                    .build(),
+                NodeBuilder::new("participant")
+                    .attr("jid", "222@s.whatsapp.net")
+                    .attr("type", "admin")
+                    .build(),
+                participant("333@s.whatsapp.net", None),
+            ])
+            .build();
+
+        let participants = parse_participants(&group);
+        assert_eq!(participants.len(), 3);
+        assert!(participants[0].is_super_admin && !participants[0].is_admin);
+        assert!(participants[1].is_admin);
+        assert!(!participants[2].is_admin && !participants[2].is_super_admin);
+    }
+
+    #[test]
+    fn parses_participating_groups() {
+        let group = |id: &str, subject: &str| {
+            NodeBuilder::new("group")
+                .attr("id", id)
+                .attr("subject", subject)
+                .build()
+        };
+        let groups = NodeBuilder::new("groups")
+            .children(vec![
+                group("120363000000000001", "Team"),
+                group("120363000000000002@g.us", "Family"),
+            ])
+            .build();
+
+        let groups = parse_participating_groups(&iq_response(vec![groups]));
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].0, jid("120363000000000001@g.us"));
+        assert_eq!(groups[0].1, "Team");
+        assert_eq!(groups[1].0, jid("120363000000000002@g.us"));
+    }
+
+    #[test]
+    fn parses_group_from_invite_info() {
+        let group = NodeBuilder::new("group")
+            .attr("id", "120363000000000001")
+            .attr("subject", "Team")
+            .build();
+
+        let group_jid = parse_invite_group(&iq_response(vec![group]));
+
+        assert_eq!(group_jid, Some(jid("120363000000000001@g.us")));
+        assert_eq!(parse_invite_group(&iq_response(vec![])), None);
+    }
+
+    #[test]
+    fn diffs_participants_between_snapshots() {
+        let snapshot = |users: &[&str]| {
+            let group = NodeBuilder::new("group")
+                .children(
+                    users
+                        .iter()
+                        .map(|user| participant(&format!("{}@s.whatsapp.net", user), None))
+                        .collect::<Vec<_>>(),
+                )
+                .build();
+            GroupMetadata {
+                jid: jid("120363000000000001@g.us"),
+                subject: "Team".to_string(),
+                participant_count: users.len(),
+                is_community: false,
+                linked_subgroups: Vec::new(),
+                approval_required: false,
+                participants: parse_participants(&group),
+                owner: None,
+                ephemeral: None,
+            }
+        };
+
+        let diff = diff_participants(&snapshot(&["111", "222"]), &snapshot(&["222", "333"]));
+
+        assert_eq!(diff.added, vec![jid("333@s.whatsapp.net")]);
+        assert_eq!(diff.removed, vec![jid("111@s.whatsapp.net")]);
+    }
+
+    #[test]
+    fn plans_adds_and_removals_towards_the_desired_members() {
+        let group = NodeBuilder::new("group")
+            .children(vec![
+                participant("111@s.whatsapp.net", None),
+                participant("222@s.whatsapp.net", None),
+            ])
+            .build();
+        let metadata = GroupMetadata {
+            jid: jid("120363000000000001@g.us"),
+            subject: "Team".to_string(),
+            participant_count: 2,
+            is_community: false,
+            linked_subgroups: Vec::new(),
+            approval_required: false,
+            participants: parse_participants(&group),
+            owner: None,
+            ephemeral: None,
+        };
+
+        let plan = membership_plan(
+            &metadata,
+            &[jid("222@s.whatsapp.net"), jid("333@s.whatsapp.net")],
+        );
+
+        assert_eq!(plan.added, vec![jid("333@s.whatsapp.net")]);
+        assert_eq!(plan.removed, vec![jid("111@s.whatsapp.net")]);
+    }
+
+    #[test]
+    fn plans_lid_participants_by_their_phone_number() {
+        let group = NodeBuilder::new("group")
+            .children(vec![
+                NodeBuilder::new("participant")
+                    .attr("jid", "98765@lid")
+                    .attr("phone_number", "222@s.whatsapp.net")
+                    .build(),
+                participant("55555@lid", None),
+            ])
+            .build();
+        let mut metadata = GroupMetadata {
+            jid: jid("120363000000000001@g.us"),
+            subject: "Team".to_string(),
+            participant_count: 2,
+            is_community: false,
+            linked_subgroups: Vec::new(),
+            approval_required: false,
+            participants: parse_participants(&group),
+            owner: None,
+            ephemeral: None,
+        };
+
+        assert_eq!(metadata.unmapped_participants(), vec![&jid("55555@lid")]);
+
+        metadata.map_lids(&[(jid("333@s.whatsapp.net"), jid("55555@lid"))]);
+        let plan = membership_plan(
+            &metadata,
+            &[jid("222@s.whatsapp.net"), jid("333@s.whatsapp.net")],
+        );
+
+        assert!(metadata.unmapped_participants().is_empty());
+        assert!(plan.added.is_empty());
+        assert!(plan.removed.is_empty());
+    }
+
+    #[test]
+    fn selects_admins_outside_the_keep_list() {
+        let member = |user: &str, is_admin: bool| GroupParticipant {
+            jid: jid(&format!("{}@s.whatsapp.net", user)),
+            is_admin,
+            is_super_admin: false,
+            phone_number: None,
+        };
+        let metadata = GroupMetadata {
+            jid: jid("120363000000000001@g.us"),
+            subject: "Team".to_string(),
+            participant_count: 3,
+            is_community: false,
+            linked_subgroups: Vec::new(),
+            approval_required: false,
+            participants: vec![
+                member("111", true),
+                member("222", true),
+                member("333", false),
+            ],
+            owner: None,
+            ephemeral: None,
+        };
+
+        let demote = admins_to_demote(&metadata, &[jid("111@s.whatsapp.net")]).unwrap();
+
+        assert_eq!(demote, vec![jid("222@s.whatsapp.net")]);
+    }
+
+    #[test]
+    fn matches_lid_admins_against_the_keep_list() {
+        let admin = |jid_str: &str, phone_number: Option<&str>| GroupParticipant {
+            jid: jid(jid_str),
+            is_admin: true,
+            is_super_admin: false,
+            phone_number: phone_number.map(jid),
+        };
+        let mut metadata = GroupMetadata {
+            jid: jid("120363000000000001@g.us"),
+            subject: "Team".to_string(),
+            participant_count: 3,
+            is_community: false,
+            linked_subgroups: Vec::new(),
+            approval_required: false,
+            participants: vec![
+                admin("111@lid", Some("555@s.whatsapp.net")),
+                admin("222@lid", None),
+                admin("333@lid", Some("666@s.whatsapp.net")),
+            ],
+            owner: None,
+            ephemeral: None,
+        };
+        let keep = [jid("555@s.whatsapp.net"), jid("777@s.whatsapp.net")];
+
+        // 222@lid might be 777, so nothing is demoted
+        assert!(admins_to_demote(&metadata, &keep).is_err());
+
+        metadata.map_lids(&[(jid("777@s.whatsapp.net"), jid("222@lid"))]);
+        let demote = admins_to_demote(&metadata, &keep).unwrap();
+
+        assert_eq!(demote, vec![jid("333@lid")]);
+    }
+
+    #[test]
+    fn unexpected_response_includes_the_node_only_when_verbose() {
+        let resp = iq_response(vec![NodeBuilder::new("surprise").build()]);
+
+        let quiet = unexpected_response("<group> not found", &resp).to_string();
+        assert!(!quiet.contains("surprise"));
+
+        set_verbose_errors(true);
+        let verbose = unexpected_response("<group> not found", &resp).to_string();
+        set_verbose_errors(false);
+        assert!(verbose.contains("surprise"));
+    }
+
+    #[test]
+    fn invite_link_displays_as_web_url() {
+        let link = InviteLink::new("AbCdEfGhIjKlMnOpQrStUv");
+
+        assert_eq!(
+            link.to_string(),
+            "https://chat.whatsapp.com/AbCdEfGhIjKlMnOpQrStUv"
+        );
+        assert_eq!(
+            link.deep_link(),
+            "whatsapp://chat?code=AbCdEfGhIjKlMnOpQrStUv"
+        );
+    }
+
+    #[tokio::test]
+    async fn splits_large_adds_into_chunks() {
+        let jids: Vec<Jid> = (0..120)
+            .map(|i| jid(&format!("{}@s.whatsapp.net", 1_000_000_000 + i)))
+            .collect();
+        let mut chunk_sizes = Vec::new();
+
+        let results = in_chunks(&jids, |chunk| {
+            chunk_sizes.push(chunk.len());
+            async move { Ok(chunk.iter().map(|jid| (jid.clone(), true, None)).collect()) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), jids.len());
+        assert_eq!(chunk_sizes, vec![50, 50, 20]);
+        assert_eq!(results[119].0, jids[119]);
+    }
+
+    #[tokio::test]
+    async fn keeps_earlier_chunk_results_when_a_later_chunk_fails() {
+        let jids: Vec<Jid> = (0..120)
+            .map(|i| jid(&format!("{}@s.whatsapp.net", 1_000_000_000 + i)))
+            .collect();
+        let mut sent = 0;
+
+        let results = in_chunks(&jids, |chunk| {
+            sent += 1;
+            let fail = sent == 2;
+            async move {
+                if fail {
+                    anyhow::bail!("timed out");
+                }
+                Ok(chunk.iter().map(|jid| (jid.clone(), true, None)).collect())
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), jids.len());
+        assert!(results[..50].iter().all(|(_, success, _)| *success));
+        assert!(
+            results[50..100]
+                .iter()
+                .all(|(_, success, code)| !success && code.is_none())
+        );
+        assert!(results[100..].iter().all(|(_, success, _)| *success));
+
+        let first_failed = in_chunks(&jids, |_| async { anyhow::bail!("timed out") }).await;
+        assert!(first_failed.is_err());
+    }
+
+    #[test]
+    fn only_server_membership_ttls_are_accepted() {
+        assert!(validate_member_ttl(Duration::from_secs(7 * 24 * 3600)).is_ok());
+        assert!(validate_member_ttl(Duration::from_secs(3600)).is_err());
+    }
+
+    #[test]
+    fn detects_bad_requests_from_both_error_paths() {
+        let sent = classify_send_error(whatsapp_rust::request::IqError::ServerError {
+            code: 400,
+            text: "bad-request".to_string(),
+        });
+        assert!(is_bad_request(&sent));
+
+        let answered = check_top_level_error(&iq_response(vec![
+            NodeBuilder::new("error")
+                .attr("code", "400")
+                .attr("text", "bad-request")
+                .build(),
+        ]))
+        .unwrap_err();
+        assert!(is_bad_request(&answered));
+    }
+
+    #[test]
+    fn maps_only_a_403_answer_to_not_authorized() {
+        let action = "transfer group ownership";
+        let sent = classify_send_error(whatsapp_rust::request::IqError::ServerError {
+            code: 403,
+            text: "forbidden".to_string(),
+        });
+        assert!(
+            map_not_authorized(sent, action, "the group owner")
+                .to_string()
+                .starts_with("Not authorized")
+        );
+
+        let answered = check_top_level_error(&iq_response(vec![
+            NodeBuilder::new("error")
+                .attr("code", "403")
+                .attr("text", "forbidden")
+                .build(),
+        ]))
+        .unwrap_err();
+        assert!(
+            map_not_authorized(answered, action, "the group owner")
+                .to_string()
+                .starts_with("Not authorized")
+        );
+
+        let timeout = anyhow::anyhow!("IQ 3.403 timed out after 30s");
+        assert_eq!(
+            map_not_authorized(timeout, action, "the group owner").to_string(),
+            "IQ 3.403 timed out after 30s"
+        );
+    }
+
+    /// A minimal JPEG header: SOI, an APP0 segment, then a baseline (SOF0) frame
+    fn jpeg(width: u16, height: u16) -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        bytes.extend_from_slice(b"JFIF\0");
+        bytes.extend_from_slice(&[0x01, 0x01, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00]);
+        bytes.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x11, 0x08]);
+        bytes.extend_from_slice(&height.to_be_bytes());
+        bytes.extend_from_slice(&width.to_be_bytes());
+        bytes.extend_from_slice(&[0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x01, 0x03, 0x11, 0x01]);
+        bytes
+    }
+
+    #[test]
+    fn reads_dimensions_from_the_start_of_frame() {
+        assert_eq!(jpeg_dimensions(&jpeg(640, 480)), Some((640, 480)));
+        assert!(validate_group_icon(&jpeg(640, 640)).is_ok());
+        assert!(
+            validate_group_icon(&jpeg(MIN_GROUP_ICON_DIMENSION, MIN_GROUP_ICON_DIMENSION)).is_ok()
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_and_non_jpeg_icons() {
+        let full = jpeg(640, 640);
+        for len in 0..full.len() {
+            assert!(validate_group_icon(&full[..len]).is_err(), "length {}", len);
+        }
+        assert!(validate_group_icon(b"\x89PNG\r\n\x1a\n").is_err());
+        assert_eq!(jpeg_dimensions(&[0xFF, 0xD8, 0xFF, 0xE0, 0xFF, 0xFF]), None);
+    }
+
+    #[test]
+    fn rejects_icons_of_the_wrong_size_or_shape() {
+        assert!(validate_group_icon(&jpeg(1024, 1024)).is_err());
+        assert!(validate_group_icon(&jpeg(96, 96)).is_err());
+        assert!(validate_group_icon(&jpeg(640, 480)).is_err());
+    }
+
+    #[test]
+    fn builds_ownership_transfer_as_superadmin_promote() {
+        let node = ownership_transfer_node(&jid("15550000001@s.whatsapp.net"));
+
+        assert_eq!(node.tag, "promote");
+        let participants = node.get_children_by_tag("participant");
+        assert_eq!(participants.len(), 1);
+        let mut parser = wacore_binary::attrs::AttrParser::new(participants[0]);
+        assert_eq!(
+            parser.optional_string("jid"),
+            Some("15550000001@s.whatsapp.net")
+        );
+        assert_eq!(parser.optional_string("type"), Some("superadmin"));
+    }
+
+    #[test]
+    fn parses_linked_subgroups_from_sub_groups_response() {
+        let sub_groups = NodeBuilder::new("sub_groups")
+            .children(vec![
+                NodeBuilder::new("group")
+                    .attr("id", "120363000000000002")
+                    .build(),
+                NodeBuilder::new("group").build(),
+                NodeBuilder::new("group")
+                    .attr("id", "120363000000000003")
+                    .build(),
+            ])
+            .build();
+
+        assert_eq!(
+            parse_linked_subgroups(&iq_response(vec![sub_groups])),
+            vec![
+                jid("120363000000000002@g.us"),
+                jid("120363000000000003@g.us")
+            ]
+        );
+        assert!(parse_linked_subgroups(&iq_response(Vec::new())).is_empty());
+    }
+
+    #[test]
+    fn parses_lid_from_usync_response() {
+        let user = |children: Vec<Node>| {
+            let list = NodeBuilder::new("list")
+                .children(vec![
+                    NodeBuilder::new("user")
+                        .attr("jid", "15550000001@s.whatsapp.net")
+                        .children(children)
+                        .build(),
+                ])
+                .build();
+            iq_response(vec![NodeBuilder::new("usync").children(vec![list]).build()])
+        };
+        let lid = NodeBuilder::new("lid")
+            .attr("val", "123456789012345@lid")
+            .build();
+
+        assert_eq!(
+            parse_usync_lid(&user(vec![lid])),
+            Some(jid("123456789012345@lid"))
+        );
+        assert_eq!(parse_usync_lid(&user(vec![])), None);
+    }
+
+    #[test]
+    fn parses_push_name_from_usync_response() {
+        // Each protocol of the query answers with a child of the same name
+        let user = |notify: Option<&str>| {
+            let mut notify_node = NodeBuilder::new("notify").build();
+            notify_node.content = notify.map(|name| NodeContent::Bytes(name.as_bytes().to_vec()));
+            let user = NodeBuilder::new("user")
+                .attr("jid", "15550000001@s.whatsapp.net")
+                .children(vec![notify_node, NodeBuilder::new("business").build()])
+                .build();
+            let list = NodeBuilder::new("list").children(vec![user]).build();
+            let result = NodeBuilder::new("result").build();
+            iq_response(vec![
+                NodeBuilder::new("usync")
+                    .attr("mode", "query")
+                    .children(vec![result, list])
+                    .build(),
+            ])
+        };
+
+        assert_eq!(
+            parse_usync_name(&user(Some("Amina"))),
+            Some("Amina".to_string())
+        );
+        assert_eq!(parse_usync_name(&user(Some("  "))), None);
+        assert_eq!(parse_usync_name(&user(None)), None);
+    }
+
+    #[test]
+    fn parses_unregistered_numbers_from_usync_response() {
+        let user = |jid: &str, contact_type: &str| {
+            NodeBuilder::new("user")
+                .attr("jid", jid)
+                .children(vec![
+                    NodeBuilder::new("contact")
+                        .attr("type", contact_type)
+                        .build(),
+                ])
+                .build()
+        };
+        let list = NodeBuilder::new("list")
+            .children(vec![
+                user("15550000001@s.whatsapp.net", "in"),
+                user("15550000002@s.whatsapp.net", "out"),
+            ])
+            .build();
+        let resp = iq_response(vec![NodeBuilder::new("usync").children(vec![list]).build()]);
 
-        Ok(format!("https://chat.whatsapp.com/{}", invite_code))
+        assert_eq!(
+            parse_usync_unregistered(&resp),
+            vec![jid("15550000002@s.whatsapp.net")]
+        );
     }
 }
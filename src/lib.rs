@@ -1,2 +1,9 @@
+pub mod cli;
+pub mod config;
+pub mod connection;
 pub mod groups;
 pub mod member_utils;
+pub mod output;
+pub mod pause;
+pub mod report;
+pub mod session;
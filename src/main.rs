@@ -1,33 +1,555 @@
-mod groups;
-
-use groups::GroupManagement;
 use lazy_static::lazy_static;
 use qrcode::QrCode;
 use qrcode::render::unicode;
+use serde::Deserialize;
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use wacore::types::events::Event;
 use wacore_binary::jid::Jid;
 use waproto::whatsapp as wa;
+use whatsapp_invites::cli::{CliArgs, parse_duration};
+use whatsapp_invites::config::{
+    ACCOUNTS_FILE, CampaignConfig, RunConfig, Settings, account_db_path, load_accounts,
+    load_campaign, load_settings, settings_file,
+};
+use whatsapp_invites::connection;
+use whatsapp_invites::groups::{
+    self, GroupManagement, MAX_GROUP_SIZE, MAX_PARTICIPANTS_PER_IQ, diff_participants,
+    membership_plan,
+};
+use whatsapp_invites::member_utils::{
+    HistoryRotation, MessageSettings, add_members_batch, add_members_chunked, add_one,
+    convert_member_list, default_invite_template, demote_admins_except, duplicate_json_key,
+    estimate_run, existing_state_files, finalize_member_addition, find_ghost_members,
+    load_consent_file, load_invite_message_template, load_last_run, load_retry_queue,
+    load_watch_progress, member_entry_key, output_path, parse_phones_csv, remove_members_batch,
+    save_failed_invites, save_invalid_phones, save_last_run, save_watch_progress,
+    send_invite_messages, set_history_rotation, set_output_dir, spread_delay,
+    validate_invite_image,
+};
+use whatsapp_invites::output;
+use whatsapp_invites::report::{DEFAULT_REPORT_FILE, RunReport};
+use whatsapp_invites::say;
+use whatsapp_invites::session;
 use whatsapp_rust::bot::Bot;
 use whatsapp_rust::store::SqliteStore;
 use whatsapp_rust_tokio_transport::TokioWebSocketTransportFactory;
 use whatsapp_rust_ureq_http_client::UreqHttpClient;
 
+/// Options that never take a value
+const SWITCHES: &[&str] = &[
+    "jsonl",
+    "force",
+    "dry-run",
+    "estimate",
+    "yes",
+    "allow-partial",
+    "shuffle",
+    "no-invite-fallback",
+    "all-failed",
+    "verify-409",
+    "verify-adds",
+    "skip-previously-added",
+    "resume-retries",
+    "ignore-failures",
+    "include-session",
+    "verbose",
+    "quiet",
+    "no-color",
+    "prune",
+    "no-qr",
+    "lid-fallback",
+];
+
+/// Exit code when some members could not be added
+const EXIT_FAILURES: i32 = 2;
+
+/// Exit code when the run was aborted (account restricted or not a group admin)
+const EXIT_FATAL: i32 = 3;
+
+/// Exit code when the session isn't paired (or was logged out) and can't be paired now
+const EXIT_UNPAIRED: i32 = 4;
+
+/// Default minimum time between two runs (none), set with WA_MIN_RUN_INTERVAL_SECS
+const DEFAULT_MIN_RUN_INTERVAL_SECS: i64 = 0;
+
 lazy_static! {
-    static ref INVITE_LINK: String = {
-        let args: Vec<String> = std::env::args().collect();
-        if args.len() < 2 {
-            eprintln!("Usage: {} <invite_link_or_group_jid>", args[0]);
-            eprintln!("Example: {} https://chat.whatsapp.com/XXXXX", args[0]);
-            eprintln!("Or:      {} 1234567890-1234567890@g.us", args[0]);
-            eprintln!("\nNote: Members are added one by one with 5 second delays");
-            eprintln!("      Rate limit errors (429) are automatically retried after 30 seconds");
+    static ref CLI_ARGS: CliArgs = CliArgs::parse(std::env::args().skip(1), SWITCHES)
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    static ref CAMPAIGN: CampaignConfig = match CLI_ARGS.value("campaign") {
+        Some(path) => load_campaign(path).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }),
+        None => CampaignConfig::default(),
+    };
+    static ref PHONES: PhoneList = load_phone_numbers();
+    static ref INVITE_LINK: String =
+        match CLI_ARGS.positional.first().or(CAMPAIGN.group_jid.as_ref()) {
+            Some(input) => input.clone(),
+            None => print_usage_and_exit(),
+        };
+    static ref SETTINGS: Settings = load_settings().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    static ref DELAY_SECS: u64 = cli_value("delay")
+        .or(CAMPAIGN.delay_secs)
+        .or(MESSAGE_SETTINGS.delay_secs)
+        .or(SETTINGS.delay_secs)
+        .unwrap_or(5);
+    static ref MESSAGE_FILE: String = cli_value("message-file")
+        .or_else(|| CAMPAIGN.message_file.clone())
+        .or_else(|| SETTINGS.message_file.clone())
+        .unwrap_or_else(|| "message.txt".to_string());
+    static ref INVITE_LANG: String = match CLI_ARGS.value("lang") {
+        Some(lang) if default_invite_template(lang).is_none() => {
+            eprintln!(
+                "⚠️  No built-in invite message for \"{}\", using English (available: en, fr, es, ar, pt)",
+                lang
+            );
+            "en".to_string()
+        }
+        Some(lang) => lang.to_string(),
+        None => "en".to_string(),
+    };
+    static ref MESSAGE_SETTINGS: MessageSettings =
+        load_invite_message_template(&MESSAGE_FILE, &INVITE_LANG).0;
+    static ref DB_PATH: String = match CLI_ARGS.value("account") {
+        Some(_) if CLI_ARGS.value("db-path").is_some() => {
+            eprintln!("Error: --account and --db-path can't be used together");
+            std::process::exit(1);
+        }
+        Some(account) => account_db_path(account).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }),
+        None => cli_value("db-path")
+            .or_else(|| SETTINGS.db_path.clone())
+            .unwrap_or_else(|| "whatsapp.db".to_string()),
+    };
+    static ref OUTPUT_DIR: Option<String> =
+        cli_value("output-dir").or_else(|| SETTINGS.output_dir.clone());
+    static ref MAX_RETRIES: usize = cli_value("max-retries")
+        .or(SETTINGS.max_retries)
+        .unwrap_or(2);
+    static ref SHUFFLE_SEED: Option<u64> = match cli_value("shuffle-seed") {
+        Some(seed) => Some(seed),
+        None if CLI_ARGS.flag("shuffle") => Some(rand::random()),
+        None => None,
+    };
+    static ref COUNTRY_CODE: Option<String> =
+        cli_value("country-code").or_else(|| SETTINGS.country_code.clone());
+    static ref WELCOME_MESSAGE: Option<String> = CLI_ARGS.value("welcome-file").map(|path| {
+        fs::read_to_string(path).unwrap_or_else(|e| {
+            eprintln!("Error: Failed to read {}: {}", path, e);
+            std::process::exit(1);
+        })
+    });
+    static ref BATCH_SIZE: usize = {
+        let requested: usize = cli_value("batch-size").unwrap_or(1);
+        let batch_size = requested.clamp(1, MAX_PARTICIPANTS_PER_IQ);
+        if batch_size != requested {
+            eprintln!(
+                "⚠️  --batch-size must be between 1 and {}, using {}",
+                MAX_PARTICIPANTS_PER_IQ, batch_size
+            );
+        }
+        batch_size
+    };
+    static ref DRY_RUN: bool = CAMPAIGN.dry_run(CLI_ARGS.flag("dry-run"));
+    static ref PROXY: Option<ureq::Proxy> = configure_proxy();
+}
+
+fn print_usage_and_exit() -> ! {
+    let program = std::env::args().next().unwrap_or_default();
+    eprintln!("Usage: {} <invite_link_or_group_jid> [options]", program);
+    eprintln!("Example: {} https://chat.whatsapp.com/XXXXX", program);
+    eprintln!("Or:      {} 1234567890-1234567890@g.us", program);
+    eprintln!("Or:      {} --campaign campaign.json", program);
+    eprintln!(
+        "Or:      {} remove <group_jid> <phones_file> [--yes]",
+        program
+    );
+    eprintln!(
+        "Or:      {} ensure <group_jid> <phones_file> [--prune] [--yes]",
+        program
+    );
+    eprintln!("Or:      {} broadcast <group_jid> <phones_file>", program);
+    eprintln!("Or:      {} clean <group_jid> [--yes]", program);
+    eprintln!("Or:      {} preflight <group_jid>", program);
+    eprintln!("Or:      {} groups", program);
+    eprintln!(
+        "Or:      {} watch <group_jid> <phones_file> [--poll-secs <n>]",
+        program
+    );
+    eprintln!("Or:      {} diff <group_jid>", program);
+    eprintln!(
+        "Or:      {} demote-all-except <group_jid> <phone,phone,...>",
+        program
+    );
+    eprintln!("Or:      {} init [--force]", program);
+    eprintln!("Or:      {} reset [--include-session] [--yes]", program);
+    eprintln!("Or:      {} config", program);
+    eprintln!("Or:      {} accounts", program);
+    eprintln!("Or:      {} export-session <file>", program);
+    eprintln!("Or:      {} import-session <file> [--force]", program);
+    eprintln!(
+        "Or:      {} invite-link <group_jid> [--png <file>]",
+        program
+    );
+    eprintln!(
+        "Or:      {} retry <run_report.json> [--all-failed] [--since <time>]",
+        program
+    );
+    eprintln!(
+        "\nExit codes: 0 = all added or skipped, 2 = some adds failed, 3 = aborted, 4 = not paired"
+    );
+    eprintln!("\nOptions:");
+    eprintln!("  --campaign <file>      Read group, phones and options from a JSON campaign file");
+    eprintln!("  --delay <secs>         Seconds to wait between members (default: 5)");
+    eprintln!("  --message-file <file>  Invite message template (default: message.txt)");
+    eprintln!(
+        "  --lang <code>          Built-in invite language without a message file: en, fr, es, ar, pt"
+    );
+    eprintln!(
+        "  --phones <file>        Phone list (JSON or CSV) instead of phones.json; repeatable"
+    );
+    eprintln!("  --db-path <file>       Session database (default: whatsapp.db)");
+    eprintln!("  --account <name>       Use the session database registered in accounts.json");
+    eprintln!(
+        "  --proxy <url>          Send HTTP requests through this proxy (default: $HTTPS_PROXY)"
+    );
+    eprintln!(
+        "  --output-dir <dir>     Directory for generated files (default: current directory)"
+    );
+    eprintln!("  --max-retries <n>      Retries for rate-limited adds (default: 2)");
+    eprintln!("  --country-code <cc>    Country code for numbers written with a leading 0");
+    eprintln!("  --dry-run              Show what would be done without connecting");
+    eprintln!("  --test-number <phone>  Only add this number and check it joined (smoke test)");
+    eprintln!(
+        "  --estimate             Estimate the run time and rate-limit risk without connecting"
+    );
+    eprintln!("  --no-invite-fallback   Don't DM an invite link to members that couldn't be added");
+    eprintln!(
+        "  --invite-format <fmt>  Send invites as \"text\" (default) or a group invite \"card\""
+    );
+    eprintln!("  --invite-image <file>  Attach a JPEG/PNG image to invite messages");
+    eprintln!("  --welcome-file <file>  Post this message in the group after adding members");
+    eprintln!("  --webhook <url>        POST the run report as JSON to this URL when done");
+    eprintln!("  --report-csv <file>    Also write the per-member results as CSV");
+    eprintln!("  --metrics-file <file>  Write Prometheus metrics of the run to this file");
+    eprintln!("  --audit-log <file>     Append a JSON line per add attempt to this file");
+    eprintln!("  --label <name>         Tag the run report, added_members.json and metrics");
+    eprintln!("  --only-from <jid>      Only add numbers already in this reference group");
+    eprintln!("  --require-consent-file <file>  Refuse numbers missing from this JSON or CSV list");
+    eprintln!("  --shuffle              Randomize member order and jitter delays");
+    eprintln!("  --batch-size <n>       Add up to n members per request (default: 1, max: 50)");
+    eprintln!("  --spread-over <time>   Spread the adds evenly over e.g. 6h instead of --delay");
+    eprintln!("  --max-duration <mins>  Stop adding members after this many minutes");
+    eprintln!("  --iq-timeout <secs>    Give up on a group request after this long (default: 30)");
+    eprintln!("  --ignore-failures      Exit 0 even when some members could not be added");
+    eprintln!("  --max-consecutive-failures <n>  Abort the run after n failed adds in a row");
+    eprintln!("  --assign-role admin    Promote every added member to admin after the run");
+    eprintln!("  --member-ttl <time>    Add members for 24h, 168h or 2160h only (experimental)");
+    eprintln!("  --lid-fallback         Retry numbers rejected with 400 by their LID");
+    eprintln!("  --no-qr                Exit instead of showing a QR code when not paired");
+    eprintln!("  --verify-409           Re-check \"already in group\" answers and retry if stale");
+    eprintln!(
+        "  --verify-adds          Re-check the group after the run for adds that didn't stick"
+    );
+    eprintln!(
+        "  --resume-retries       Add members queued by earlier runs' retryable failures first"
+    );
+    eprintln!("  --skip-previously-added  Skip numbers added to this group by earlier runs");
+    eprintln!("  --shuffle-seed <u64>   Like --shuffle, with a fixed seed to replay a run");
+    eprintln!(
+        "  --checkpoint-every <n> Write run_report.json every n members (default: 25, 0 = end only)"
+    );
+    eprintln!(
+        "  --jsonl                Emit one JSON object per member on stdout (human output goes to stderr)"
+    );
+    eprintln!("  --quiet                Only print summaries, warnings and errors");
+    eprintln!("  --no-color             Never colorize output (also honors NO_COLOR)");
+    eprintln!("  --verbose              Include the raw server response in parse errors");
+    eprintln!(
+        "  --force                Run even if the previous run finished less than the cooldown ago"
+    );
+    eprintln!("  --yes                  Skip the confirmation prompt of destructive commands");
+    eprintln!(
+        "  --allow-partial        Add only the members that fit when the group would exceed its cap"
+    );
+    eprintln!(
+        "\nDefaults can be set in ./whatsapp-inviter.toml or $XDG_CONFIG_HOME/whatsapp-inviter.toml"
+    );
+    eprintln!("\nNote: Members are added one by one with 5 second delays");
+    eprintln!("      Rate limit errors (429) are automatically retried after 30 seconds");
+    std::process::exit(1);
+}
+
+/// Parse a CLI option value, exiting with an error message when it's malformed
+fn cli_value<T: std::str::FromStr>(name: &str) -> Option<T>
+where
+    T::Err: std::fmt::Display,
+{
+    CLI_ARGS.parse_value(name).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    })
+}
+
+/// Environment variable holding the phone list as a JSON array, used instead of phones.json
+const PHONES_ENV_VAR: &str = "WA_PHONES_JSON";
+
+/// Contents of a phone list: one list for the CLI-provided group, or one list per group
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum PhoneList {
+    /// `["123", ...]`, added to the group given on the command line
+    Single(Vec<String>),
+    /// `{"GROUP_JID": ["123", ...], ...}`, one batch per group
+    PerGroup(BTreeMap<String, Vec<String>>),
+}
+
+impl PhoneList {
+    /// The flat list of phones, exiting with an error for a per-group file
+    fn into_single(self, source: &str) -> Vec<String> {
+        match self {
+            PhoneList::Single(phones) => phones,
+            PhoneList::PerGroup(_) => {
+                eprintln!("{} must be a JSON array of phone numbers here", source);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Phone numbers to process: from the campaign file, `--phones` files,
+/// `WA_PHONES_JSON`, or phones.json
+fn load_phone_numbers() -> PhoneList {
+    let phone_files = CLI_ARGS.values("phones");
+    if !phone_files.is_empty() {
+        return PhoneList::Single(merge_phone_files(&phone_files));
+    }
+
+    if let Some(phones) = &CAMPAIGN.phones {
+        return PhoneList::Single(phones.clone());
+    }
+
+    if let Ok(data) = std::env::var(PHONES_ENV_VAR) {
+        return parse_phones(&data, PHONES_ENV_VAR);
+    }
+
+    read_phones_file("phones.json")
+}
+
+/// Read several phone list files into one list, dropping entries seen in an earlier file
+///
+/// Prints how many numbers each file brought and the merged total.
+fn merge_phone_files(paths: &[&str]) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut merged = Vec::new();
+
+    for path in paths {
+        let phones = read_phones_file(path).into_single(path);
+        let total = phones.len();
+        let mut new = 0;
+        for phone in phones {
+            if seen.insert(member_entry_key(&phone)) {
+                merged.push(phone);
+                new += 1;
+            }
+        }
+        say!("📄 {}: {} number(s), {} new", path, total, new);
+    }
+
+    if paths.len() > 1 {
+        say!(
+            "Merged {} files: {} unique number(s)\n",
+            paths.len(),
+            merged.len()
+        );
+    }
+    merged
+}
+
+/// Read a phone list file (a JSON list, or a `.csv` file), exiting on failure
+fn read_phones_file(path: &str) -> PhoneList {
+    match fs::read_to_string(path) {
+        Ok(data) if path.to_lowercase().ends_with(".csv") => {
+            PhoneList::Single(parse_phones_csv(&data))
+        }
+        Ok(data) => parse_phones(&data, path),
+        Err(e) => {
+            eprintln!("Failed to read {}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse a phone list read from `source`, exiting on failure
+///
+/// A group listed twice in a per-group file is an error rather than losing one of
+/// its lists.
+fn parse_phones(data: &str, source: &str) -> PhoneList {
+    if let Some(group) = duplicate_json_key(data) {
+        eprintln!(
+            "Failed to parse {}: group {} is listed twice; merge its phone numbers into one list",
+            source, group
+        );
+        std::process::exit(1);
+    }
+
+    match serde_json::from_str(data) {
+        Ok(phones) => phones,
+        Err(e) => {
+            eprintln!(
+                "Failed to parse {}: {} (expected an array of phone numbers, or an object of group JID to phone numbers)",
+                source, e
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Convert phone numbers (or full JIDs) to JIDs, printing every rejected entry
+///
+/// Returns the valid JIDs and the rejected raw entries.
+fn phones_to_jids(phones: &[String]) -> (Vec<Jid>, Vec<String>) {
+    let (jids, rejected) = convert_member_list(phones, COUNTRY_CODE.as_deref());
+
+    if !rejected.is_empty() {
+        eprintln!("✗ Rejected {} invalid entries:", rejected.len());
+        for (raw, reason) in &rejected {
+            eprintln!("   - {:?}: {}", raw, reason);
+        }
+    }
+
+    (jids, rejected.into_iter().map(|(raw, _)| raw).collect())
+}
+
+/// Colorize when human output goes to a terminal, unless `--no-color` or `NO_COLOR` is set
+fn use_color() -> bool {
+    if CLI_ARGS.flag("no-color") || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
+    }
+
+    // Human-readable lines move to stderr in JSON-lines mode
+    if output::jsonl_enabled() {
+        std::io::stderr().is_terminal()
+    } else {
+        std::io::stdout().is_terminal()
+    }
+}
+
+/// The proxy for HTTP requests from `--proxy` or `HTTPS_PROXY`, exiting on a malformed URL
+///
+/// The websocket transport has no proxy support and still connects directly.
+fn configure_proxy() -> Option<ureq::Proxy> {
+    let proxy_url: String = cli_value("proxy").or_else(|| {
+        ["HTTPS_PROXY", "https_proxy"]
+            .iter()
+            .find_map(|name| std::env::var(name).ok())
+    })?;
+
+    let proxy = match ureq::Proxy::new(&proxy_url) {
+        Ok(proxy) => proxy,
+        Err(e) => {
+            eprintln!("Error: invalid proxy URL {}: {}", proxy_url, e);
             std::process::exit(1);
         }
-        args[1].clone()
     };
+
+    say!("🌐 HTTP requests go through the proxy at {}", proxy_url);
+    eprintln!(
+        "⚠️  Only HTTP requests are proxied: the websocket connection to WhatsApp is still direct"
+    );
+    Some(proxy)
+}
+
+/// HTTP client for the bot, using the configured proxy
+fn http_client() -> UreqHttpClient {
+    let agent = ureq::Agent::config_builder()
+        .proxy(PROXY.clone())
+        .build()
+        .new_agent();
+
+    UreqHttpClient::with_agent(agent)
+}
+
+/// Ask for a `y/N` confirmation on the terminal
+///
+/// Returns true straight away with `--yes`. Without it, a non-interactive stdin
+/// is treated as a refusal so scripts never run destructive commands by accident.
+fn confirm(prompt: &str) -> bool {
+    if CLI_ARGS.flag("yes") {
+        return true;
+    }
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!("stdin is not a terminal; pass --yes to confirm non-interactively");
+        return false;
+    }
+
+    eprint!("{} [y/N] ", prompt);
+    let _ = std::io::stderr().flush();
+
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Print the pairing QR code on the terminal
+fn print_pairing_qr(code: &str, timeout: std::time::Duration) {
+    say!(
+        "Scan this QR code to pair (valid for {}s):\n{}",
+        timeout.as_secs(),
+        render_qr(&QrCode::new(code.as_bytes()).unwrap())
+    );
+}
+
+/// Show the pairing QR code, or exit right away with `--no-qr`
+///
+/// A QR code is only requested when the session isn't paired, which in an
+/// unattended run means nobody is there to scan it.
+fn on_pairing_qr(code: &str, timeout: std::time::Duration) {
+    if CLI_ARGS.flag("no-qr") {
+        eprintln!(
+            "✗ The session in {} is not paired or has expired: re-pair required.",
+            *DB_PATH
+        );
+        eprintln!("  Run once without --no-qr and scan the QR code to pair again.");
+        std::process::exit(EXIT_UNPAIRED);
+    }
+
+    print_pairing_qr(code, timeout);
+}
+
+/// Exit when WhatsApp logged the session out (e.g. removed from Linked devices)
+fn on_logged_out() -> ! {
+    eprintln!(
+        "✗ The session in {} was logged out by WhatsApp: re-pair required.",
+        *DB_PATH
+    );
+    eprintln!("  Run again and scan the QR code (use `reset --include-session` if none is shown).");
+    std::process::exit(EXIT_UNPAIRED);
+}
+
+/// Render a QR code as terminal text
+fn render_qr(qr: &QrCode) -> String {
+    qr.render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Dark)
+        .light_color(unicode::Dense1x2::Light)
+        .build()
 }
 
 /// Extract group JID from invite link or return the JID if already provided
@@ -48,264 +570,1958 @@ fn extract_group_jid(input: &str) -> Option<String> {
     }
 }
 
+/// Refuse to start if the previous run finished less than the cooldown ago
+///
+/// The cooldown is opt-in, read from WA_MIN_RUN_INTERVAL_SECS (unset or 0 disables
+/// it). With `force` the run proceeds anyway after a warning.
+fn check_run_cooldown(force: bool) {
+    let min_interval = std::env::var("WA_MIN_RUN_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_MIN_RUN_INTERVAL_SECS);
+
+    let Some(last_run) = load_last_run() else {
+        return;
+    };
+
+    let elapsed = (chrono::Utc::now() - last_run).num_seconds();
+    if min_interval <= 0 || elapsed >= min_interval {
+        return;
+    }
+
+    let remaining = min_interval - elapsed;
+    eprintln!(
+        "⚠️  The previous run finished {}s ago (minimum interval: {}s)",
+        elapsed, min_interval
+    );
+
+    if force {
+        eprintln!("   --force given, starting anyway");
+    } else {
+        eprintln!(
+            "   Refusing to start to protect the account; try again in {}s or pass --force",
+            remaining
+        );
+        std::process::exit(1);
+    }
+}
+
+/// `--estimate`: grade the rate-limit risk of the batch without connecting
+///
+/// Uses the phone list, the effective delay (or `--spread-over` pace) and the time
+/// since the previous run in last_run.json.
+fn print_estimate() {
+    let country_code = COUNTRY_CODE.as_deref();
+    let adds = match &*PHONES {
+        PhoneList::Single(phones) => convert_member_list(phones, country_code).0.len(),
+        PhoneList::PerGroup(groups) => groups
+            .values()
+            .map(|phones| convert_member_list(phones, country_code).0.len())
+            .sum(),
+    };
+
+    let config = run_config();
+    let delay_secs = match config.spread_over {
+        Some(window) => spread_delay(window, adds),
+        None => config.delay_seconds,
+    };
+    let since_last_run =
+        load_last_run().and_then(|last_run| (chrono::Utc::now() - last_run).to_std().ok());
+
+    let estimate = estimate_run(adds, delay_secs, since_last_run, &SETTINGS.estimate);
+
+    let minutes = (estimate.duration.as_secs() + 30) / 60;
+    let duration = if minutes >= 60 {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    };
+    let mut line = format!(
+        "{} adds at {}s ≈ {}; {} 429 risk",
+        adds, delay_secs, duration, estimate.risk
+    );
+    if !estimate.advice.is_empty() {
+        line.push_str(", ");
+        line.push_str(&estimate.advice.join(", "));
+    }
+    say!("{}", line);
+    output::emit_jsonl(&serde_json::json!({
+        "estimate": true,
+        "adds": adds,
+        "delay_secs": delay_secs,
+        "duration_secs": estimate.duration.as_secs(),
+        "risk": estimate.risk.to_string(),
+        "advice": estimate.advice,
+    }));
+}
+
+/// Check that an invite code looks like a real WhatsApp invite code
+///
+/// Codes are alphanumeric and (in practice) 20 to 24 characters long.
+fn validate_invite_code(code: &str) -> bool {
+    (20..=24).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Extract the invite code from a full invite URL, or return the input if it's a bare code
+fn extract_invite_code(input: &str) -> &str {
+    let code = match input.split_once("chat.whatsapp.com/") {
+        Some((_, rest)) => rest,
+        None => input,
+    };
+
+    code.split(['?', '#', '/'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+/// Re-query the group and print how the participant count changed during the run
+///
+/// Warns when the observed growth is less than half of the reported successes,
+/// which usually means adds didn't stick or members were removed meanwhile.
+async fn print_participant_trend(
+    client: &whatsapp_rust::Client,
+    group_jid: &Jid,
+    before: usize,
+    total_success: usize,
+) {
+    let after = match client.query_group_metadata(group_jid).await {
+        Ok(metadata) => metadata.participant_count,
+        Err(e) => {
+            eprintln!("⚠️  Could not re-query participant count: {}", e);
+            return;
+        }
+    };
+
+    let delta = after as i64 - before as i64;
+    say!("Participants: {} → {} ({:+})", before, after, delta);
+
+    if delta < (total_success / 2) as i64 {
+        say!(
+            "⚠️  Participant count grew by {} but {} additions were reported successful",
+            delta,
+            total_success
+        );
+        say!("   Some members may have been removed or the additions didn't take effect");
+    }
+}
+
+/// Make sure the members to add fit under WhatsApp's group size cap
+///
+/// Listed numbers that are already in the group don't count. When the others
+/// don't fit, abort unless `--allow-partial` is given, in which case the list is
+/// truncated to the new members that fit.
+fn check_group_capacity(metadata: &groups::GroupMetadata, participant_jids: &mut Vec<Jid>) {
+    let current = metadata.participant_count;
+    let capacity = MAX_GROUP_SIZE.saturating_sub(current);
+    let new_members = participant_jids
+        .iter()
+        .filter(|jid| !metadata.has_participant(jid))
+        .count();
+    if new_members <= capacity {
+        return;
+    }
+
+    eprintln!(
+        "⚠️  The group has {}/{} members: only {} of the {} new member(s) can fit",
+        current, MAX_GROUP_SIZE, capacity, new_members
+    );
+
+    if capacity == 0 {
+        eprintln!("   The group is full, nothing to add.");
+        std::process::exit(1);
+    }
+
+    if !CLI_ARGS.flag("allow-partial") {
+        eprintln!(
+            "   Aborting; pass --allow-partial to add the first {} only",
+            capacity
+        );
+        std::process::exit(1);
+    }
+
+    eprintln!(
+        "   --allow-partial given, adding the first {} only",
+        capacity
+    );
+    let mut kept_new = 0;
+    participant_jids.retain(|jid| {
+        if metadata.has_participant(jid) {
+            return true;
+        }
+        kept_new += 1;
+        kept_new <= capacity
+    });
+}
+
 /// Send invite links to participants as fallback
 async fn send_invite_links(
     client: &whatsapp_rust::Client,
     invite_link: &str,
     participant_jids: &[Jid],
 ) {
+    let code = extract_invite_code(invite_link);
+    if !validate_invite_code(code) {
+        eprintln!("✗ Invalid invite link: {}", invite_link);
+        eprintln!(
+            "   Expected https://chat.whatsapp.com/<code> with an alphanumeric code of 20-24 characters"
+        );
+        eprintln!("   No invite messages were sent.");
+        return;
+    }
+    let invite_link = format!("https://chat.whatsapp.com/{}", code);
+    let (_, template) = load_invite_message_template(&MESSAGE_FILE, &INVITE_LANG);
+    let invite_message = template.replace("{link}", &invite_link);
+
     for jid in participant_jids {
         let message = wa::Message {
-            conversation: Some(format!("Join our group: {}", invite_link)),
+            conversation: Some(invite_message.clone()),
             ..Default::default()
         };
 
         match client.send_message(jid.clone(), message).await {
-            Ok(_) => println!("📧 Sent invite link to {}", jid),
+            Ok(_) => say!("📧 Sent invite link to {}", jid),
             Err(e) => eprintln!("Failed to send invite to {}: {}", jid, e),
         }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    if !Path::new("phones.json").exists() {
-        eprintln!("phones.json not found. Please create a JSON array of phone numbers.");
-        std::process::exit(1);
+/// Parse a group JID given on the command line (must end with @g.us)
+fn parse_group_jid(input: &str) -> Option<Jid> {
+    if !input.ends_with("@g.us") {
+        return None;
+    }
+    input.parse().ok()
+}
+
+/// Print one line of the preflight checklist, green when it passed and red otherwise
+fn print_check(passed: bool, label: &str) {
+    if passed {
+        say!("{}", output::green(&format!("✓ {}", label)));
+    } else {
+        say!("{}", output::red(&format!("✗ {}", label)));
     }
+}
 
-    let backend = Arc::new(SqliteStore::new("whatsapp.db").await?);
+/// `preflight <group_jid>`: check that a campaign against the group can work
+///
+/// Runs the checks in order (session, group metadata, admin rights, invite link)
+/// and stops at the first failure.
+async fn run_preflight(client: &whatsapp_rust::Client) -> i32 {
+    let Some(group_input) = CLI_ARGS.positional.get(1) else {
+        print_usage_and_exit();
+    };
 
-    let transport_factory = TokioWebSocketTransportFactory::new();
-    let http_client = UreqHttpClient::new();
+    // Reaching this point means the stored session connected without pairing
+    print_check(true, "Session is paired and connected");
 
-    let mut bot = Bot::builder()
-        .with_backend(backend)
-        .with_transport_factory(transport_factory)
-        .with_http_client(http_client)
-        .on_event(|event, client| async move {
-            let invite_link = &*INVITE_LINK;
-            println!("{:?}", event);
-            match event {
-                Event::PairingQrCode { code, timeout } => {
-                    let qr = QrCode::new(code.as_bytes()).unwrap();
-                    let image = qr.render::<unicode::Dense1x2>()
-                        .dark_color(unicode::Dense1x2::Dark)
-                        .light_color(unicode::Dense1x2::Light)
-                        .build();
-                    println!("Scan this QR code to pair (valid for {}s):\n{}", timeout.as_secs(), image);
-                }
-                Event::Connected(_) => {
-                    println!("Bot connected!");
-
-                    // Read phone numbers from file
-                    let phone_numbers: Vec<String> = match fs::read_to_string("phones.json") {
-                        Ok(data) => match serde_json::from_str(&data) {
-                            Ok(phones) => phones,
-                            Err(e) => {
-                                eprintln!("Failed to parse phones.json: {}", e);
-                                return;
-                            }
-                        },
-                        Err(e) => {
-                            eprintln!("Failed to read phones.json: {}", e);
-                            return;
-                        }
-                    };
-
-                    // Convert phone numbers to JIDs
-                    let mut participant_jids = Vec::new();
-                    for phone_str in &phone_numbers {
-                        let full_jid = format!("{}@s.whatsapp.net", phone_str);
-                        match full_jid.parse::<Jid>() {
-                            Ok(jid) => participant_jids.push(jid),
-                            Err(_) => {
-                                eprintln!("Invalid phone number: {}", phone_str);
-                            }
-                        }
-                    }
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        print_check(false, &format!("Invalid group JID: {}", group_input));
+        return 1;
+    };
 
-                    if participant_jids.is_empty() {
-                        eprintln!("No valid phone numbers to add!");
-                        std::process::exit(1);
-                    }
+    let metadata = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => {
+            print_check(
+                true,
+                &format!(
+                    "Group reachable: {} ({} participants)",
+                    metadata.subject, metadata.participant_count
+                ),
+            );
+            metadata
+        }
+        Err(e) => {
+            print_check(false, &format!("Cannot reach group {}: {}", group_jid, e));
+            return 1;
+        }
+    };
 
-                    // Try to extract group JID from input
-                    let group_jid_result = extract_group_jid(invite_link);
-
-                    if let Some(group_jid_str) = group_jid_result {
-                        // Direct addition method (preferred)
-                        println!("\n=== Adding members directly to group ===");
-                        match group_jid_str.parse::<Jid>() {
-                            Ok(group_jid) => {
-                                // Query group metadata to display group name
-                                if let Ok(metadata) = client.query_group_metadata(&group_jid).await {
-                                    println!("Group Name: {}", metadata.subject);
-                                    println!("Current Participants: {}", metadata.participant_count);
-                                }
-                                println!("Group JID: {}", group_jid);
-                                println!("Adding {} members one by one (5 second delay between each)...\n", participant_jids.len());
-
-                                let mut success_count = 0;
-                                let mut failed_jids = Vec::new();
-                                let mut invalid_phones = Vec::new();
-
-                                for (index, jid) in participant_jids.iter().enumerate() {
-                                    println!("=== Adding member {}/{} ===", index + 1, participant_jids.len());
-
-                                    let mut retry_count = 0;
-                                    let max_retries = 2;
-                                    let mut added = false;
-
-                                    while retry_count <= max_retries && !added {
-                                        if retry_count > 0 {
-                                            println!("   Retry attempt {}/{}", retry_count, max_retries);
-                                        }
-
-                                        match client.add_group_participants(&group_jid, std::slice::from_ref(jid)).await {
-                                            Ok(results) => {
-                                                for (jid, success, error_code) in results {
-                                                    if success {
-                                                        println!("✓ Successfully added: {}", jid);
-                                                        success_count += 1;
-                                                        added = true;
-                                                    } else {
-                                                        // Check if it's a rate limit error (429)
-                                                        if let Some(429) = error_code
-                                                            && retry_count < max_retries {
-                                                                println!("⚠️  Rate limited (429), waiting 30 seconds before retry...");
-                                                                tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                                                                retry_count += 1;
-                                                                continue;
-                                                            }
-
-                                                        println!("✗ Failed to add: {} (error: {:?})", jid, error_code);
-
-                                                        // Track invalid phones (400 errors)
-                                                        if let Some(400) = error_code {
-                                                            let phone = jid.to_string().replace("@s.whatsapp.net", "").replace("@lid", "");
-                                                            invalid_phones.push(phone);
-                                                        }
-
-                                                        failed_jids.push(jid);
-                                                        added = true;
-
-                                                        // Explain common errors
-                                                        if let Some(code) = error_code {
-                                                            match code {
-                                                                400 => println!("   → Bad request (invalid phone number - will be saved to invalid_phones.json)"),
-                                                                403 => println!("   → Not authorized (you may not be an admin)"),
-                                                                409 => println!("   → User is already in the group"),
-                                                                404 => println!("   → User not found or doesn't have WhatsApp"),
-                                                                429 => println!("   → Rate limit exceeded (max retries reached)"),
-                                                                _ => {}
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                            Err(e) => {
-                                                let error_msg = e.to_string();
-                                                // Check if error message contains rate limit
-                                                if (error_msg.contains("429") || error_msg.contains("rate-overlimit"))
-                                                    && retry_count < max_retries {
-                                                        println!("⚠️  Rate limited, waiting 30 seconds before retry...");
-                                                        tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
-                                                        retry_count += 1;
-                                                        continue;
-                                                    }
-
-                                                // Track invalid phones (400 errors)
-                                                if error_msg.contains("400") || error_msg.contains("bad-request") {
-                                                    let phone = jid.to_string().replace("@s.whatsapp.net", "").replace("@lid", "");
-                                                    invalid_phones.push(phone.clone());
-                                                    eprintln!("✗ Failed to add {}: {} (saved to invalid_phones.json)", jid, e);
-                                                } else {
-                                                    eprintln!("✗ Failed to add {}: {}", jid, e);
-                                                }
-
-                                                failed_jids.push(jid.clone());
-                                                added = true;
-                                            }
-                                        }
-
-                                        if !added {
-                                            retry_count += 1;
-                                        }
-                                    }
-
-                                    // Wait 5 seconds before next member
-                                    if index < participant_jids.len() - 1 {
-                                        println!("Waiting 5 seconds before next member...\n");
-                                        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-                                    }
-                                }
-
-                                println!("\n=== Summary ===");
-                                println!("✓ Successfully added: {}", success_count);
-                                println!("✗ Failed: {}", failed_jids.len());
-
-                                // Save invalid phones to JSON file
-                                if !invalid_phones.is_empty() {
-                                    use std::path::Path;
-
-                                    let file_path = "invalid_phones.json";
-                                    let mut all_invalid_phones: Vec<String> = Vec::new();
-
-                                    // Load existing invalid phones if file exists
-                                    if Path::new(file_path).exists()
-                                        && let Ok(existing_data) = fs::read_to_string(file_path)
-                                            && let Ok(existing_phones) = serde_json::from_str::<Vec<String>>(&existing_data) {
-                                                all_invalid_phones = existing_phones;
-                                            }
-
-                                    // Add new invalid phones (avoid duplicates)
-                                    for phone in invalid_phones {
-                                        if !all_invalid_phones.contains(&phone) {
-                                            all_invalid_phones.push(phone);
-                                        }
-                                    }
-
-                                    // Save to file
-                                    if let Ok(json_data) = serde_json::to_string_pretty(&all_invalid_phones) {
-                                        if let Err(e) = fs::write(file_path, json_data) {
-                                            eprintln!("⚠️  Failed to save invalid_phones.json: {}", e);
-                                        } else {
-                                            println!("\n📝 Saved {} invalid phone numbers to invalid_phones.json", all_invalid_phones.len());
-                                        }
-                                    }
-                                }
-
-                                // Fallback: send invite links to failed additions
-                                if !failed_jids.is_empty() {
-                                    println!("\n=== Sending invite links to failed additions ===");
-                                    for jid in failed_jids {
-                                        let message = wa::Message {
-                                            conversation: Some(format!("Join our group: {}", invite_link)),
-                                            ..Default::default()
-                                        };
-
-                                        match client.send_message(jid.clone(), message).await {
-                                            Ok(_) => println!("📧 Sent invite link to {}", jid),
-                                            Err(e) => eprintln!("Failed to send invite to {}: {}", jid, e),
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Invalid group JID: {}", e);
-                                eprintln!("Falling back to sending invite links...\n");
-                                send_invite_links(&client, invite_link, &participant_jids).await;
-                            }
-                        }
-                    } else {
-                        // Invite link method (fallback)
-                        println!("\n=== Sending invite links ===");
-                        println!("Note: Provide group JID (e.g., 1234567890-1234567890@g.us) to add members directly\n");
-                        send_invite_links(&client, invite_link, &participant_jids).await;
-                    }
+    let own_jids: Vec<Jid> = [client.get_pn().await, client.get_lid().await]
+        .into_iter()
+        .flatten()
+        .collect();
+    if own_jids.iter().any(|own_jid| metadata.i_am_admin(own_jid)) {
+        print_check(true, "This account is an admin of the group");
+    } else {
+        print_check(false, "This account is not an admin of the group");
+        return 1;
+    }
+
+    match client.get_group_invite_link(&group_jid).await {
+        Ok(link) => print_check(true, &format!("Invite link available: {}", link)),
+        Err(e) => {
+            print_check(false, &format!("Cannot fetch the invite link: {}", e));
+            return 1;
+        }
+    }
+
+    say!("\n{}", output::green("All checks passed, ready to run."));
+    0
+}
+
+/// `invite-link <group_jid>`: print the group's invite link and its QR code
+///
+/// With `--png <file>`, the QR code is also saved as a PNG image.
+async fn run_invite_link(client: &whatsapp_rust::Client) -> i32 {
+    let Some(group_input) = CLI_ARGS.positional.get(1) else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let link = match client.get_group_invite_link(&group_jid).await {
+        Ok(link) => link,
+        Err(e) => {
+            eprintln!("✗ Failed to fetch the invite link: {}", e);
+            return 1;
+        }
+    };
+
+    let qr = match QrCode::new(link.url().as_bytes()) {
+        Ok(qr) => qr,
+        Err(e) => {
+            eprintln!("✗ Failed to encode the invite link as a QR code: {}", e);
+            return 1;
+        }
+    };
+
+    say!("Invite link: {}", link);
+    say!("App link:    {}\n", link.deep_link());
+    say!("{}", render_qr(&qr));
+
+    if let Some(png_path) = CLI_ARGS.value("png") {
+        let image = qr
+            .render::<image::Luma<u8>>()
+            .min_dimensions(512, 512)
+            .build();
+        if let Err(e) = image.save(png_path) {
+            eprintln!("✗ Failed to write {}: {}", png_path, e);
+            return 1;
+        }
+        say!("📝 Saved the QR code to {}", png_path);
+    }
+
+    0
+}
+
+/// `diff <group_jid>`: snapshot the participants, wait for Enter, then print who joined or left
+async fn run_diff(client: &whatsapp_rust::Client) -> i32 {
+    let Some(group_input) = CLI_ARGS.positional.get(1) else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let before = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("✗ Failed to fetch group info: {}", e);
+            return 1;
+        }
+    };
+    say!(
+        "📸 Snapshot of \"{}\" taken ({} participants).",
+        before.subject,
+        before.participant_count
+    );
+    eprint!("Press Enter to take the second snapshot...");
+    let _ = std::io::stderr().flush();
+
+    let mut line = String::new();
+    let stdin_read = tokio::task::spawn_blocking(move || {
+        std::io::stdin().lock().read_line(&mut line).map(|_| ())
+    });
+    if !matches!(stdin_read.await, Ok(Ok(()))) {
+        eprintln!("\nFailed to read from stdin");
+        return 1;
+    }
+
+    let after = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("✗ Failed to fetch group info: {}", e);
+            return 1;
+        }
+    };
+
+    let diff = diff_participants(&before, &after);
+    say!(
+        "\n=== {} → {} participants ===",
+        before.participant_count,
+        after.participant_count
+    );
+    for jid in &diff.added {
+        say!("+ {}", jid);
+    }
+    for jid in &diff.removed {
+        say!("- {}", jid);
+    }
+    if diff.added.is_empty() && diff.removed.is_empty() {
+        say!("No changes.");
+    }
+    output::emit_jsonl(&serde_json::json!({
+        "group": group_jid.to_string(),
+        "added": diff.added.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        "removed": diff.removed.iter().map(ToString::to_string).collect::<Vec<_>>(),
+    }));
+
+    0
+}
+
+/// `groups`: list every group this account belongs to
+async fn run_groups(client: &whatsapp_rust::Client) -> i32 {
+    let groups = match client.list_my_groups().await {
+        Ok(groups) => groups,
+        Err(e) => {
+            eprintln!("✗ Failed to list groups: {}", e);
+            return 1;
+        }
+    };
 
-                    std::process::exit(0);
+    let group_jids: Vec<Jid> = groups.iter().map(|(jid, _)| jid.clone()).collect();
+    let metadata = groups::GroupOps::query_many_group_metadata(client, &group_jids).await;
+
+    say!("This account is in {} group(s):\n", groups.len());
+    for ((jid, subject), (_, metadata)) in groups.iter().zip(metadata) {
+        let participants = match metadata {
+            Ok(metadata) => Some(metadata.participant_count),
+            Err(e) => {
+                eprintln!("⚠️  Failed to query {}: {}", jid, e);
+                None
+            }
+        };
+        say!(
+            "  {}  {}  ({} members)",
+            jid,
+            subject,
+            participants.map_or("?".to_string(), |count| count.to_string())
+        );
+        output::emit_jsonl(&serde_json::json!({
+            "jid": jid.to_string(),
+            "subject": subject,
+            "participants": participants,
+        }));
+    }
+    0
+}
+
+/// `demote-all-except <group_jid> <keep_phones>`: remove admin from everyone not kept
+///
+/// `keep_phones` is a comma-separated list. This account always keeps its own role.
+async fn run_demote_all_except(client: &whatsapp_rust::Client) -> i32 {
+    let (Some(group_input), Some(keep_input)) =
+        (CLI_ARGS.positional.get(1), CLI_ARGS.positional.get(2))
+    else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let keep_phones: Vec<String> = keep_input
+        .split(',')
+        .map(|phone| phone.trim().to_string())
+        .filter(|phone| !phone.is_empty())
+        .collect();
+    let (mut keep, rejected) = phones_to_jids(&keep_phones);
+    // A typo in the keep list must not demote the admin it was meant to protect
+    if !rejected.is_empty() {
+        eprintln!("Fix the keep list and try again; nobody was demoted.");
+        return 1;
+    }
+    keep.extend(
+        [client.get_pn().await, client.get_lid().await]
+            .into_iter()
+            .flatten(),
+    );
+
+    let results = match demote_admins_except(client, &group_jid, &keep).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("✗ Failed to demote admins: {}", e);
+            return 1;
+        }
+    };
+
+    if results.is_empty() {
+        say!("Nobody to demote: every admin is in the keep list.");
+        return 0;
+    }
+
+    let mut failed = 0;
+    for (jid, success, error_code) in &results {
+        if *success {
+            say!("✓ Demoted {}", jid);
+        } else {
+            eprintln!("✗ Failed to demote {} (error code: {:?})", jid, error_code);
+            failed += 1;
+        }
+    }
+
+    say!("\nDemoted: {}, failed: {}", results.len() - failed, failed);
+    if failed > 0 { EXIT_FAILURES } else { 0 }
+}
+
+/// Sample phone list written by `init`; the `//` entry is a note, skipped when reading
+const SAMPLE_PHONES_JSON: &str = r#"[
+  "// Replace these with the numbers to add: international format, without + or leading 0",
+  "15550100001",
+  "15550100002"
+]
+"#;
+
+/// `init`: write a sample phones.json and invite message to start from
+///
+/// Refuses to overwrite either file unless `--force` is given.
+fn run_init() -> i32 {
+    let message = default_invite_template(&INVITE_LANG).unwrap_or_default();
+    let files = [
+        ("phones.json".to_string(), SAMPLE_PHONES_JSON.to_string()),
+        (MESSAGE_FILE.clone(), format!("{}\n", message)),
+    ];
+
+    let existing: Vec<&str> = files
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .filter(|path| Path::new(path).exists())
+        .collect();
+    if !existing.is_empty() && !CLI_ARGS.flag("force") {
+        eprintln!(
+            "✗ {} already exist(s); pass --force to overwrite. Nothing was written.",
+            existing.join(" and ")
+        );
+        return 1;
+    }
+
+    for (path, contents) in &files {
+        if let Err(e) = fs::write(path, contents) {
+            eprintln!("✗ Failed to write {}: {}", path, e);
+            return 1;
+        }
+        say!("📝 Wrote {}", path);
+    }
+    say!("\nPut the numbers to add in phones.json and adjust the message, keeping {{link}}.");
+    say!("Then run with the group's invite link or JID to add them.");
+    0
+}
+
+/// `reset`: delete the generated state files, after listing them and confirming
+///
+/// The session database is only deleted with `--include-session`, since that
+/// requires pairing again.
+fn run_reset() -> i32 {
+    let mut files = existing_state_files();
+
+    if CLI_ARGS.flag("include-session") {
+        for suffix in ["", "-wal", "-shm"] {
+            let path = PathBuf::from(format!("{}{}", *DB_PATH, suffix));
+            if path.exists() {
+                files.push(path);
+            }
+        }
+    }
+
+    if files.is_empty() {
+        say!("Nothing to reset: no state files found.");
+        return 0;
+    }
+
+    say!("The following files will be deleted:");
+    for file in &files {
+        say!("  - {}", file.display());
+    }
+    if !CLI_ARGS.flag("include-session") {
+        say!(
+            "(the session in {} is kept; pass --include-session to delete it too)",
+            *DB_PATH
+        );
+    }
+
+    if !confirm(&format!("Delete {} file(s)?", files.len())) {
+        eprintln!("Aborted, nothing was deleted.");
+        return 1;
+    }
+
+    let mut exit_code = 0;
+    for file in &files {
+        match fs::remove_file(file) {
+            Ok(()) => say!("🗑️  Removed {}", file.display()),
+            Err(e) => {
+                eprintln!("✗ Failed to remove {}: {}", file.display(), e);
+                exit_code = 1;
+            }
+        }
+    }
+    exit_code
+}
+
+/// The origin of a setting: the label of the first source that sets it, else "default"
+fn setting_origin(sources: &[(bool, String)]) -> String {
+    sources
+        .iter()
+        .find(|(present, _)| *present)
+        .map(|(_, label)| label.clone())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// `config`: print the effective settings and where each value comes from
+///
+/// Sources are listed from highest to lowest precedence. Nothing is sent over
+/// the network.
+fn run_config_command() -> i32 {
+    let config = run_config();
+
+    let campaign = CLI_ARGS
+        .value("campaign")
+        .map(|path| format!("from campaign {}", path))
+        .unwrap_or_default();
+    let from_file = settings_file()
+        .map(|path| format!("from {}", path.display()))
+        .unwrap_or_default();
+    let front_matter = format!("from {} front matter", *MESSAGE_FILE);
+    let cli = || "from CLI".to_string();
+    let env = |name: &str| (std::env::var_os(name).is_some(), format!("from {}", name));
+
+    match settings_file() {
+        Some(path) => say!("Settings file: {}", path.display()),
+        None => say!("Settings file: none found"),
+    }
+    say!("");
+
+    let rows = [
+        (
+            "delay_secs",
+            DELAY_SECS.to_string(),
+            setting_origin(&[
+                (CLI_ARGS.value("delay").is_some(), cli()),
+                (CAMPAIGN.delay_secs.is_some(), campaign.clone()),
+                (MESSAGE_SETTINGS.delay_secs.is_some(), front_matter.clone()),
+                env("WA_DELAY_SECS"),
+                (SETTINGS.delay_secs.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "message_file",
+            MESSAGE_FILE.clone(),
+            setting_origin(&[
+                (CLI_ARGS.value("message-file").is_some(), cli()),
+                (CAMPAIGN.message_file.is_some(), campaign.clone()),
+                env("WA_MESSAGE_FILE"),
+                (SETTINGS.message_file.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "invite_format",
+            format!("{:?}", config.invite_format).to_lowercase(),
+            setting_origin(&[
+                (CLI_ARGS.value("invite-format").is_some(), cli()),
+                (MESSAGE_SETTINGS.invite_format.is_some(), front_matter),
+            ]),
+        ),
+        (
+            "db_path",
+            DB_PATH.clone(),
+            setting_origin(&[
+                (
+                    CLI_ARGS.value("account").is_some(),
+                    format!("from {} (--account)", ACCOUNTS_FILE),
+                ),
+                (CLI_ARGS.value("db-path").is_some(), cli()),
+                env("WA_DB_PATH"),
+                (SETTINGS.db_path.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "output_dir",
+            OUTPUT_DIR.clone().unwrap_or_else(|| ".".to_string()),
+            setting_origin(&[
+                (CLI_ARGS.value("output-dir").is_some(), cli()),
+                env("WA_OUTPUT_DIR"),
+                (SETTINGS.output_dir.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "max_retries",
+            MAX_RETRIES.to_string(),
+            setting_origin(&[
+                (CLI_ARGS.value("max-retries").is_some(), cli()),
+                env("WA_MAX_RETRIES"),
+                (SETTINGS.max_retries.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "country_code",
+            COUNTRY_CODE.clone().unwrap_or_else(|| "(none)".to_string()),
+            setting_origin(&[
+                (CLI_ARGS.value("country-code").is_some(), cli()),
+                env("WA_COUNTRY_CODE"),
+                (SETTINGS.country_code.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "abort_on_not_admin",
+            config.abort_on_not_admin.to_string(),
+            setting_origin(&[
+                env("WA_ABORT_ON_NOT_ADMIN"),
+                (SETTINGS.abort_on_not_admin.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "lid_fallback",
+            (CLI_ARGS.flag("lid-fallback") || SETTINGS.lid_fallback == Some(true)).to_string(),
+            setting_origin(&[
+                (CLI_ARGS.flag("lid-fallback"), cli()),
+                (SETTINGS.lid_fallback.is_some(), from_file.clone()),
+            ]),
+        ),
+        (
+            "country_profiles",
+            if config.country_profiles.is_empty() {
+                "(none)".to_string()
+            } else {
+                config
+                    .country_profiles
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+            setting_origin(&[(!SETTINGS.country_profiles.is_empty(), from_file)]),
+        ),
+        (
+            "batch_size",
+            BATCH_SIZE.to_string(),
+            setting_origin(&[(CLI_ARGS.value("batch-size").is_some(), cli())]),
+        ),
+        (
+            "checkpoint_every",
+            config.checkpoint_every.to_string(),
+            setting_origin(&[(CLI_ARGS.value("checkpoint-every").is_some(), cli())]),
+        ),
+    ];
+
+    let width = rows
+        .iter()
+        .map(|(name, _, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    for (name, value, origin) in &rows {
+        say!("{:<width$} = {} ({})", name, value, origin, width = width);
+    }
+    0
+}
+
+/// `accounts`: list the accounts registered in accounts.json
+fn run_accounts() -> i32 {
+    let accounts = match load_accounts() {
+        Ok(accounts) => accounts,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    if accounts.is_empty() {
+        say!(
+            "No accounts registered. Create {} mapping names to session databases.",
+            ACCOUNTS_FILE
+        );
+        return 0;
+    }
+
+    let width = accounts.keys().map(String::len).max().unwrap_or(0);
+    for (name, db_path) in &accounts {
+        let status = if Path::new(db_path).exists() {
+            "found"
+        } else {
+            "missing, pairs on first use"
+        };
+        say!("{:<width$}  {}  ({})", name, db_path, status, width = width);
+    }
+    0
+}
+
+/// Environment variable holding the session export passphrase
+const PASSPHRASE_ENV_VAR: &str = "WA_SESSION_PASSPHRASE";
+
+/// The session export passphrase, from `WA_SESSION_PASSPHRASE` or the terminal
+///
+/// With `confirm_twice`, an interactively typed passphrase must be entered twice.
+fn read_passphrase(confirm_twice: bool) -> Option<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV_VAR) {
+        return Some(passphrase);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        eprintln!(
+            "stdin is not a terminal; set {} to provide the passphrase",
+            PASSPHRASE_ENV_VAR
+        );
+        return None;
+    }
+
+    let passphrase = rpassword::prompt_password("Passphrase: ").ok()?;
+    if confirm_twice {
+        let again = rpassword::prompt_password("Repeat passphrase: ").ok()?;
+        if again != passphrase {
+            eprintln!("Passphrases don't match.");
+            return None;
+        }
+    }
+    Some(passphrase)
+}
+
+/// `export-session <file>`: write the paired session as an encrypted, portable file
+fn run_export_session() -> i32 {
+    let Some(out_path) = CLI_ARGS.positional.get(1) else {
+        eprintln!("Usage: export-session <file>");
+        return 1;
+    };
+    let Some(passphrase) = read_passphrase(true) else {
+        return 1;
+    };
+
+    match session::export_session(&DB_PATH, out_path, &passphrase) {
+        Ok(count) => {
+            say!(
+                "🔐 Exported the session in {} ({} file(s)) to {}",
+                *DB_PATH,
+                count,
+                out_path
+            );
+            say!("Keep it private: anyone with the file and passphrase can use this account.");
+            0
+        }
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            1
+        }
+    }
+}
+
+/// `import-session <file>`: restore an exported session into `--db-path`
+fn run_import_session() -> i32 {
+    let Some(blob_path) = CLI_ARGS.positional.get(1) else {
+        eprintln!("Usage: import-session <file> [--force]");
+        return 1;
+    };
+    let Some(passphrase) = read_passphrase(false) else {
+        return 1;
+    };
+
+    match session::import_session(blob_path, &DB_PATH, &passphrase, CLI_ARGS.flag("force")) {
+        Ok(count) => {
+            say!(
+                "🔓 Restored the session from {} into {} ({} file(s))",
+                blob_path,
+                *DB_PATH,
+                count
+            );
+            say!("Stop any other machine still using this session before connecting.");
+            0
+        }
+        Err(e) => {
+            eprintln!("✗ {}", e);
+            1
+        }
+    }
+}
+
+/// `ensure <group_jid> <phones_file>`: make the group contain the listed members
+///
+/// Adds the missing ones; with `--prune`, also removes participants not in the file
+/// (after confirmation). This account is never removed.
+async fn run_ensure(client: &whatsapp_rust::Client) -> i32 {
+    let (Some(group_input), Some(phones_file)) =
+        (CLI_ARGS.positional.get(1), CLI_ARGS.positional.get(2))
+    else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let prune = CLI_ARGS.flag("prune");
+    let (desired, rejected) =
+        phones_to_jids(&read_phones_file(phones_file).into_single(phones_file));
+    // A typo would otherwise remove the member it was meant to keep
+    if prune && !rejected.is_empty() {
+        eprintln!("Fix {} and try again; nothing was changed.", phones_file);
+        return 1;
+    }
+
+    let mut metadata = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("Failed to query group metadata: {}", e);
+            return 1;
+        }
+    };
+
+    if prune && !metadata.unmapped_participants().is_empty() {
+        let mut mapping = Vec::new();
+        for phone_jid in &desired {
+            if let Ok(Some(lid)) = client.resolve_lid(phone_jid).await {
+                mapping.push((phone_jid.clone(), lid));
+            }
+        }
+        metadata.map_lids(&mapping);
+    }
+    // Without a phone number a LID participant can't be told apart from a wanted member
+    let unmapped = metadata.unmapped_participants();
+    if prune && !unmapped.is_empty() {
+        eprintln!(
+            "✗ {} participant(s) of \"{}\" are only known by LID and can't be matched to {}:",
+            unmapped.len(),
+            metadata.subject,
+            phones_file
+        );
+        for jid in &unmapped {
+            eprintln!("    {}", jid);
+        }
+        eprintln!("Refusing --prune; nothing was changed.");
+        return 1;
+    }
+
+    let own_jids: Vec<Jid> = [client.get_pn().await, client.get_lid().await]
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut plan = membership_plan(&metadata, &desired);
+    plan.removed
+        .retain(|jid| !own_jids.iter().any(|own_jid| own_jid.user == jid.user));
+
+    say!("Plan for \"{}\" ({}):", metadata.subject, group_jid);
+    say!("  Add {} member(s)", plan.added.len());
+    for jid in &plan.added {
+        say!("    + {}", jid);
+    }
+    if prune {
+        say!("  Remove {} member(s)", plan.removed.len());
+        for jid in &plan.removed {
+            say!("    - {}", jid);
+        }
+    } else if !plan.removed.is_empty() {
+        say!(
+            "  {} participant(s) not in {} are kept (pass --prune to remove them)",
+            plan.removed.len(),
+            phones_file
+        );
+    }
+
+    let to_remove = if prune { plan.removed } else { Vec::new() };
+    if plan.added.is_empty() && to_remove.is_empty() {
+        say!(
+            "\n✓ Nothing to do, the group already matches {}",
+            phones_file
+        );
+        return 0;
+    }
+
+    if !to_remove.is_empty()
+        && !confirm(&format!(
+            "Remove {} member(s) from \"{}\"?",
+            to_remove.len(),
+            metadata.subject
+        ))
+    {
+        eprintln!("Aborted, nothing was changed.");
+        return 1;
+    }
+
+    let config = run_config();
+    let mut failed = 0;
+
+    if !plan.added.is_empty() {
+        let stats = add_members_batch(client, &group_jid, &plan.added, &config).await;
+        failed += stats.total_failed;
+        finalize_member_addition(client, &group_jid, stats, &config).await;
+    }
+
+    if !to_remove.is_empty() {
+        say!("");
+        let stats = remove_members_batch(client, &group_jid, &to_remove, &config).await;
+        failed += stats.total_failed;
+    }
+
+    if failed > 0 { EXIT_FAILURES } else { 0 }
+}
+
+/// `broadcast <group_jid> <phones_file>`: DM the invite link to the listed numbers
+///
+/// Nobody is added; numbers that already received an invite are skipped.
+async fn run_broadcast(client: &whatsapp_rust::Client) -> i32 {
+    let (Some(group_input), Some(phones_file)) =
+        (CLI_ARGS.positional.get(1), CLI_ARGS.positional.get(2))
+    else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let Some(member_jids) =
+        valid_member_jids(&read_phones_file(phones_file).into_single(phones_file))
+    else {
+        eprintln!("No valid phone numbers to send the invite to!");
+        return 1;
+    };
+
+    let config = run_config();
+    let invite_stats = send_invite_messages(client, &group_jid, &member_jids, &config).await;
+
+    say!(
+        "\nInvited: {}, already invited: {}, failed: {}",
+        invite_stats.sent.len(),
+        invite_stats.skipped.len(),
+        invite_stats.failed.len()
+    );
+    if !invite_stats.sent.is_empty() || !invite_stats.failed.is_empty() {
+        match save_failed_invites(&invite_stats) {
+            Ok(total) => say!(
+                "📝 {} member(s) awaiting an invite in failed_invites.json",
+                total
+            ),
+            Err(e) => eprintln!("⚠️  Failed to save failed_invites.json: {}", e),
+        }
+    }
+
+    if invite_stats.failed.is_empty() {
+        0
+    } else {
+        EXIT_FAILURES
+    }
+}
+
+/// `remove <group_jid> <phones_file>`: confirm, then remove the listed members
+async fn run_remove(client: &whatsapp_rust::Client) -> i32 {
+    let (Some(group_input), Some(phones_file)) =
+        (CLI_ARGS.positional.get(1), CLI_ARGS.positional.get(2))
+    else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let (member_jids, _) = phones_to_jids(&read_phones_file(phones_file).into_single(phones_file));
+
+    if member_jids.is_empty() {
+        eprintln!("No valid phone numbers to remove!");
+        return 1;
+    }
+
+    let group_name = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => metadata.subject,
+        Err(e) => {
+            eprintln!("Failed to query group metadata: {}", e);
+            return 1;
+        }
+    };
+
+    let prompt = format!(
+        "Remove {} member(s) from \"{}\" ({})?",
+        member_jids.len(),
+        group_name,
+        group_jid
+    );
+    if !confirm(&prompt) {
+        eprintln!("Aborted, no members were removed.");
+        return 1;
+    }
+
+    let config = RunConfig {
+        delay_seconds: *DELAY_SECS,
+        max_retries: *MAX_RETRIES,
+        ..RunConfig::default()
+    };
+    let stats = remove_members_batch(client, &group_jid, &member_jids, &config).await;
+
+    if stats.total_failed > 0 { 1 } else { 0 }
+}
+
+/// `clean <group_jid>`: remove participants whose number is no longer on WhatsApp
+async fn run_clean(client: &whatsapp_rust::Client) -> i32 {
+    let Some(group_input) = CLI_ARGS.positional.get(1) else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    say!("🔎 Checking which participants are still on WhatsApp...");
+    let ghosts = match find_ghost_members(client, &group_jid).await {
+        Ok(ghosts) => ghosts,
+        Err(e) => {
+            eprintln!("✗ Failed to check the participants: {}", e);
+            return 1;
+        }
+    };
+
+    if ghosts.is_empty() {
+        say!("No ghost members found, nothing to remove.");
+        return 0;
+    }
+
+    say!("{} participant(s) are no longer on WhatsApp:", ghosts.len());
+    for jid in &ghosts {
+        say!("  - {}", jid);
+    }
+    if !confirm(&format!("Remove {} ghost member(s)?", ghosts.len())) {
+        eprintln!("Aborted, no members were removed.");
+        return 1;
+    }
+
+    let config = RunConfig {
+        delay_seconds: *DELAY_SECS,
+        max_retries: *MAX_RETRIES,
+        ..RunConfig::default()
+    };
+    let stats = remove_members_batch(client, &group_jid, &ghosts, &config).await;
+    say!(
+        "\n🧹 Removed {} of {} ghost member(s)",
+        stats.total_removed,
+        ghosts.len()
+    );
+
+    if stats.total_failed > 0 { 1 } else { 0 }
+}
+
+/// `--test-number <phone>`: add one known-good number and check it joined
+///
+/// A smoke test of the whole pipeline (session, group access, admin rights, add
+/// and verification) before a big run. The phone list is left untouched.
+async fn run_test_number(client: &whatsapp_rust::Client) -> i32 {
+    let phone = CLI_ARGS.value("test-number").unwrap_or_default();
+    let Some(group_jid) = resolve_group_target(client, &INVITE_LINK).await else {
+        eprintln!(
+            "✗ FAIL: not a group JID or resolvable invite link: {}",
+            *INVITE_LINK
+        );
+        return 1;
+    };
+    let (jids, _) = phones_to_jids(&[phone.to_string()]);
+    let Some(member_jid) = jids.into_iter().next() else {
+        eprintln!("✗ FAIL: {} is not a valid phone number", phone);
+        return 1;
+    };
+
+    say!("🧪 Smoke test: adding {} to {}", member_jid, group_jid);
+    let metadata = match client.query_group_metadata(&group_jid).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("✗ FAIL: can't read the group: {}", e);
+            return EXIT_FAILURES;
+        }
+    };
+    say!("✓ Group found: \"{}\"", metadata.subject);
+
+    let member_lid = client.resolve_lid(&member_jid).await.ok().flatten();
+    let already_member = metadata.has_participant(&member_jid)
+        || member_lid.is_some_and(|lid| metadata.has_participant(&lid));
+    if already_member {
+        eprintln!(
+            "✗ Inconclusive: {} is already in the group; remove it or pick another number",
+            member_jid
+        );
+        return 1;
+    }
+
+    let config = run_config();
+    let policy = config.retry_policy_for(&member_jid.user);
+    let result = add_one(client, &group_jid, &member_jid, &policy).await;
+    if !result.success {
+        eprintln!(
+            "✗ FAIL: the add was refused ({})",
+            result.error_text.as_deref().unwrap_or("unknown error")
+        );
+        return if result.account_restricted || result.error_code == Some(403) {
+            EXIT_FATAL
+        } else {
+            EXIT_FAILURES
+        };
+    }
+    say!("✓ Add reported successful");
+
+    match client.is_participant(&group_jid, &member_jid).await {
+        Ok(true) => {
+            say!(
+                "{}",
+                output::green("✅ PASS: the number is in the group, ready for the full run")
+            );
+            0
+        }
+        Ok(false) => {
+            eprintln!("✗ FAIL: reported added but not found in the group");
+            EXIT_FAILURES
+        }
+        Err(e) => {
+            eprintln!("✗ FAIL: couldn't re-check the participants: {}", e);
+            EXIT_FAILURES
+        }
+    }
+}
+
+/// The settings of a member-addition run, from the CLI, env and settings file
+fn run_config() -> RunConfig {
+    RunConfig {
+        delay_seconds: *DELAY_SECS,
+        max_retries: *MAX_RETRIES,
+        message_file: MESSAGE_FILE.clone(),
+        invite_lang: INVITE_LANG.clone(),
+        invite_format: cli_value("invite-format")
+            .or(MESSAGE_SETTINGS.invite_format)
+            .unwrap_or_default(),
+        invite_image: cli_value("invite-image"),
+        send_invite: !CLI_ARGS.flag("no-invite-fallback"),
+        report_file: output_path(DEFAULT_REPORT_FILE),
+        report_csv: cli_value("report-csv"),
+        metrics_file: cli_value("metrics-file"),
+        audit_log: cli_value("audit-log"),
+        webhook: cli_value("webhook"),
+        checkpoint_every: cli_value("checkpoint-every").unwrap_or(25),
+        spread_over: CLI_ARGS.value("spread-over").map(|window| {
+            parse_duration(window).unwrap_or_else(|e| {
+                eprintln!("Error: Invalid value for --spread-over: {}", e);
+                std::process::exit(1);
+            })
+        }),
+        member_ttl: CLI_ARGS.value("member-ttl").map(|ttl| {
+            parse_duration(ttl)
+                .and_then(|ttl| {
+                    groups::validate_member_ttl(ttl)
+                        .map(|()| ttl)
+                        .map_err(|e| e.to_string())
+                })
+                .unwrap_or_else(|e| {
+                    eprintln!("Error: Invalid value for --member-ttl: {}", e);
+                    std::process::exit(1);
+                })
+        }),
+        max_duration: cli_value::<u64>("max-duration")
+            .map(|mins| std::time::Duration::from_secs(mins * 60)),
+        label: CLI_ARGS.value("label").unwrap_or_default().to_string(),
+        shuffle_seed: *SHUFFLE_SEED,
+        only_from: cli_value("only-from"),
+        consented_phones: CLI_ARGS.value("require-consent-file").map(|path| {
+            load_consent_file(path).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                eprintln!("   --require-consent-file is set, so no member is added without it.");
+                std::process::exit(1);
+            })
+        }),
+        skip_previously_added: CLI_ARGS.flag("skip-previously-added"),
+        verify_409: CLI_ARGS.flag("verify-409"),
+        verify_adds: CLI_ARGS.flag("verify-adds"),
+        abort_on_not_admin: SETTINGS.abort_on_not_admin.unwrap_or(true),
+        max_consecutive_failures: cli_value("max-consecutive-failures")
+            .filter(|&max_failures: &usize| max_failures > 0),
+        assign_role: cli_value("assign-role"),
+        welcome_message: WELCOME_MESSAGE.clone(),
+        country_profiles: SETTINGS.country_profiles.clone(),
+        ..RunConfig::default()
+    }
+}
+
+/// Default seconds between two checks of a watched phones file
+const DEFAULT_WATCH_POLL_SECS: u64 = 30;
+
+/// `watch <group_jid> <phones_file>`: add new entries of the file as they appear
+///
+/// Entries already processed are remembered in `watch_progress-<group>.json`, so
+/// restarting the watcher doesn't re-add anyone. Runs until interrupted.
+async fn run_watch(client: &whatsapp_rust::Client) -> i32 {
+    let (Some(group_input), Some(phones_file)) =
+        (CLI_ARGS.positional.get(1), CLI_ARGS.positional.get(2))
+    else {
+        print_usage_and_exit();
+    };
+
+    let Some(group_jid) = parse_group_jid(group_input) else {
+        eprintln!("Invalid group JID: {}", group_input);
+        return 1;
+    };
+
+    let config = run_config();
+    // Polling no faster than the member delay keeps rounds apart by at least that much
+    let poll_interval = std::time::Duration::from_secs(
+        cli_value("poll-secs")
+            .unwrap_or(DEFAULT_WATCH_POLL_SECS)
+            .max(config.delay_seconds),
+    );
+    let mut processed = load_watch_progress(&group_jid);
+
+    say!(
+        "👀 Watching {} for new numbers to add to {} (every {}s, {} already processed)",
+        phones_file,
+        group_jid,
+        poll_interval.as_secs(),
+        processed.len()
+    );
+
+    loop {
+        let phones = fs::read_to_string(phones_file)
+            .map_err(|e| format!("Failed to read {}: {}", phones_file, e))
+            .and_then(|data| {
+                if phones_file.to_lowercase().ends_with(".csv") {
+                    return Ok(parse_phones_csv(&data));
+                }
+                serde_json::from_str::<Vec<String>>(&data)
+                    .map_err(|e| format!("Failed to parse {}: {}", phones_file, e))
+            });
+
+        match phones {
+            Ok(phones) => {
+                let new_entries: Vec<String> = phones
+                    .into_iter()
+                    .filter(|phone| !processed.contains(phone))
+                    .collect();
+
+                if !new_entries.is_empty() {
+                    say!(
+                        "\n📥 {} new number(s) in {}",
+                        new_entries.len(),
+                        phones_file
+                    );
+
+                    if let Some(member_jids) = valid_member_jids(&new_entries) {
+                        let stats =
+                            add_members_batch(client, &group_jid, &member_jids, &config).await;
+                        finalize_member_addition(client, &group_jid, stats, &config).await;
+                    }
+
+                    processed.extend(new_entries);
+                    if let Err(e) = save_watch_progress(&group_jid, &processed) {
+                        eprintln!("⚠️  Failed to save watch progress: {}", e);
+                    }
+                }
+            }
+            // The file may be mid-write; try again on the next round
+            Err(e) => eprintln!("⚠️  {}", e),
+        }
+
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/// `retry <run_report.json>`: add again the members that failed with a transient error
+///
+/// Only rate limits, timeouts and network failures are retried unless `--all-failed`
+/// is given. Honors the run cooldown and exits like a normal run.
+async fn run_retry(client: &whatsapp_rust::Client) -> i32 {
+    let Some(report_path) = CLI_ARGS.positional.get(1) else {
+        print_usage_and_exit();
+    };
+
+    let previous = match RunReport::load(report_path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return 1;
+        }
+    };
+
+    let Some(group_jid) = parse_group_jid(&previous.group) else {
+        eprintln!("Invalid group JID in {}: {}", report_path, previous.group);
+        return 1;
+    };
+
+    // Entries without a timestamp can't be dated, so they are kept
+    let cutoff = CLI_ARGS.value("since").map(|window| {
+        let window = parse_duration(window).unwrap_or_else(|e| {
+            eprintln!("Error: Invalid value for --since: {}", e);
+            std::process::exit(1);
+        });
+        chrono::TimeDelta::from_std(window)
+            .ok()
+            .and_then(|window| chrono::Utc::now().checked_sub_signed(window))
+            .unwrap_or(chrono::DateTime::<chrono::Utc>::MIN_UTC)
+    });
+    let mut undated = 0;
+
+    let include_permanent = CLI_ARGS.flag("all-failed");
+    let member_jids: Vec<Jid> = previous
+        .entries
+        .iter()
+        .filter(|entry| entry.is_retryable() || (include_permanent && entry.status == "failed"))
+        .filter(|entry| {
+            let Some(cutoff) = cutoff else {
+                return true;
+            };
+            entry.recorded_since(cutoff).unwrap_or_else(|| {
+                undated += 1;
+                true
+            })
+        })
+        .filter_map(|entry| entry.jid.parse().ok())
+        .collect();
+
+    if undated > 0 {
+        say!(
+            "⚠️  {} failure(s) have no timestamp and are retried regardless of --since",
+            undated
+        );
+    }
+
+    if member_jids.is_empty() {
+        say!("No retryable failures in {}", report_path);
+        return 0;
+    }
+
+    say!(
+        "Retrying {} failed member(s) from {} in group {}\n",
+        member_jids.len(),
+        report_path,
+        group_jid
+    );
+
+    let config = run_config();
+    let stats = add_members_batch(client, &group_jid, &member_jids, &config).await;
+    let outcome = GroupOutcome {
+        added: stats.total_success,
+        skipped: stats.total_skipped,
+        failed: stats.total_failed,
+        account_restricted: stats.account_restricted,
+        not_admin: stats.not_admin,
+        too_many_failures: stats.too_many_failures,
+    };
+    finalize_member_addition(client, &group_jid, stats, &config).await;
+
+    finish_run(outcome.exit_code());
+}
+
+/// Run the add (or invite) flow once connected, then exit
+async fn run_add(client: Arc<whatsapp_rust::Client>) {
+    #[cfg(unix)]
+    whatsapp_invites::pause::listen_for_sigusr1();
+
+    // Read phone numbers from the campaign file, --phones, WA_PHONES_JSON or phones.json
+    let phone_numbers = match &*PHONES {
+        PhoneList::Single(phones) => phones,
+        PhoneList::PerGroup(groups) => {
+            let outcome = run_add_per_group(&client, groups).await;
+            finish_run(outcome.exit_code());
+        }
+    };
+    let invite_link = &*INVITE_LINK;
+
+    // Convert phone numbers to JIDs
+    let Some(participant_jids) = valid_member_jids(phone_numbers) else {
+        eprintln!("No valid phone numbers to add!");
+        std::process::exit(1);
+    };
+
+    // Try to extract group JID from input
+    let group_jid_result = extract_group_jid(invite_link);
+    let mut outcome = GroupOutcome::default();
+
+    if let Some(group_jid_str) = group_jid_result {
+        // Direct addition method (preferred)
+        match group_jid_str.parse::<Jid>() {
+            Ok(group_jid) => {
+                outcome = add_to_group(&client, &group_jid, participant_jids, &run_config()).await;
+            }
+            Err(e) => {
+                eprintln!("Invalid group JID: {}", e);
+                if CLI_ARGS.flag("no-invite-fallback") {
+                    eprintln!("Invite fallback disabled, nothing to do.");
+                } else {
+                    eprintln!("Falling back to sending invite links...\n");
+                    send_invite_links(&client, invite_link, &participant_jids).await;
+                }
+            }
+        }
+    } else {
+        // Invite link method (fallback)
+        say!("\n=== Sending invite links ===");
+        say!(
+            "Note: Provide group JID (e.g., 1234567890-1234567890@g.us) to add members directly\n"
+        );
+        send_invite_links(&client, invite_link, &participant_jids).await;
+    }
+
+    finish_run(outcome.exit_code());
+}
+
+/// Record the run for the cooldown check, then exit
+///
+/// `--ignore-failures` turns any exit code into 0.
+fn finish_run(exit_code: i32) -> ! {
+    if let Err(e) = save_last_run() {
+        eprintln!("⚠️  Failed to save last_run.json: {}", e);
+    }
+
+    if CLI_ARGS.flag("ignore-failures") {
+        std::process::exit(0);
+    }
+    std::process::exit(exit_code);
+}
+
+/// Counts from adding members to one or more groups
+#[derive(Debug, Default, Clone, Copy)]
+struct GroupOutcome {
+    added: usize,
+    skipped: usize,
+    failed: usize,
+    account_restricted: bool,
+    not_admin: bool,
+    too_many_failures: bool,
+}
+
+impl GroupOutcome {
+    /// Accumulate the outcome of another group
+    fn merge(&mut self, other: GroupOutcome) {
+        self.added += other.added;
+        self.skipped += other.skipped;
+        self.failed += other.failed;
+        self.account_restricted |= other.account_restricted;
+        self.not_admin |= other.not_admin;
+        self.too_many_failures |= other.too_many_failures;
+    }
+
+    /// 0 when every member was added or skipped, 2 on failures, 3 when aborted
+    fn exit_code(&self) -> i32 {
+        if self.account_restricted || self.not_admin || self.too_many_failures {
+            EXIT_FATAL
+        } else if self.failed > 0 {
+            EXIT_FAILURES
+        } else {
+            0
+        }
+    }
+}
+
+/// Convert phone numbers to JIDs and record rejected entries right away
+///
+/// Returns `None` when no entry is valid.
+fn valid_member_jids(phones: &[String]) -> Option<Vec<Jid>> {
+    let (participant_jids, rejected) = phones_to_jids(phones);
+
+    // Record rejected entries right away, before the batch starts
+    if !rejected.is_empty() {
+        match save_invalid_phones(&rejected) {
+            Ok(total) => say!(
+                "📝 Saved {} invalid phone numbers to invalid_phones.json\n",
+                total
+            ),
+            Err(e) => eprintln!("⚠️  Failed to save invalid_phones.json: {}", e),
+        }
+    }
+
+    (!participant_jids.is_empty()).then_some(participant_jids)
+}
+
+/// Add members directly to one group: show its metadata, run the batch, then finalize
+async fn add_to_group(
+    client: &whatsapp_rust::Client,
+    group_jid: &Jid,
+    mut participant_jids: Vec<Jid>,
+    config: &RunConfig,
+) -> GroupOutcome {
+    say!("\n=== Adding members directly to group ===");
+
+    // Query group metadata to display group name
+    let metadata_before = client.query_group_metadata(group_jid).await.ok();
+    let participants_before = metadata_before
+        .as_ref()
+        .map(|metadata| metadata.participant_count);
+    if let Some(metadata) = &metadata_before {
+        say!("Group Name: {}", metadata.subject);
+        say!("Current Participants: {}", metadata.participant_count);
+        if metadata.is_community {
+            say!(
+                "Community with {} linked sub-group(s):",
+                metadata.linked_subgroups.len()
+            );
+            for subgroup in &metadata.linked_subgroups {
+                say!("  - {}", subgroup);
+            }
+        }
+    }
+    say!("Group JID: {}", group_jid);
+
+    if CLI_ARGS.flag("resume-retries") {
+        let queued: Vec<Jid> = load_retry_queue(group_jid)
+            .into_iter()
+            .filter(|jid| !participant_jids.contains(jid))
+            .collect();
+        if !queued.is_empty() {
+            say!(
+                "🔁 Retrying {} member(s) from retry_queue.json first",
+                queued.len()
+            );
+            participant_jids.splice(0..0, queued);
+        }
+    }
+
+    if let Some(metadata) = &metadata_before {
+        check_group_capacity(metadata, &mut participant_jids);
+    }
+
+    let stats =
+        add_members_chunked(client, group_jid, &participant_jids, *BATCH_SIZE, config).await;
+    let outcome = GroupOutcome {
+        added: stats.total_success,
+        skipped: stats.total_skipped,
+        failed: stats.total_failed,
+        account_restricted: stats.account_restricted,
+        not_admin: stats.not_admin,
+        too_many_failures: stats.too_many_failures,
+    };
+
+    if let Some(before) = participants_before {
+        print_participant_trend(client, group_jid, before, stats.total_success).await;
+    }
+
+    finalize_member_addition(client, group_jid, stats, config).await;
+    outcome
+}
+
+/// Resolve a per-group key (group JID or invite link) to a group JID
+async fn resolve_group_target(client: &whatsapp_rust::Client, input: &str) -> Option<Jid> {
+    let input = input.trim();
+    if let Some(group_jid) = parse_group_jid(input) {
+        return Some(group_jid);
+    }
+
+    let code = extract_invite_code(input);
+    if !validate_invite_code(code) {
+        return None;
+    }
+
+    match client.resolve_invite_code(code).await {
+        Ok(group_jid) => Some(group_jid),
+        Err(e) => {
+            eprintln!("✗ Failed to resolve invite link {}: {}", input, e);
+            None
+        }
+    }
+}
+
+/// Run one batch per group of a `{"GROUP_JID": [phones...]}` file, then print a summary
+///
+/// Keys may also be invite links. Keys resolving to the same group are merged
+/// into one batch. Each group gets its own run report (`run_report-<group>.json`).
+/// Stops early when the account gets restricted.
+async fn run_add_per_group(
+    client: &whatsapp_rust::Client,
+    groups: &BTreeMap<String, Vec<String>>,
+) -> GroupOutcome {
+    let mut summary = Vec::new();
+    let mut total = GroupOutcome::default();
+
+    // Resolve every key first so duplicates are merged before any delay is spent
+    let mut targets: Vec<(String, Jid, Vec<String>)> = Vec::new();
+    for (group_input, phones) in groups {
+        let Some(group_jid) = resolve_group_target(client, group_input).await else {
+            eprintln!(
+                "✗ Not a group JID or resolvable invite link, skipping: {}",
+                group_input
+            );
+            summary.push((group_input.clone(), None));
+            continue;
+        };
+
+        match targets.iter_mut().find(|(_, jid, _)| *jid == group_jid) {
+            Some((first_input, _, first_phones)) => {
+                eprintln!(
+                    "⚠️  {} is the same group as {} ({}), merging their phone lists",
+                    group_input, first_input, group_jid
+                );
+                for phone in phones {
+                    if !first_phones.contains(phone) {
+                        first_phones.push(phone.clone());
+                    }
+                }
+            }
+            None => targets.push((group_input.clone(), group_jid, phones.clone())),
+        }
+    }
+
+    for (group_input, group_jid, phones) in &targets {
+        say!(
+            "\n##### Group {} ({} phones) #####",
+            group_jid,
+            phones.len()
+        );
+        let Some(participant_jids) = valid_member_jids(phones) else {
+            eprintln!("No valid phone numbers for {}, skipping", group_jid);
+            summary.push((group_input.clone(), Some(GroupOutcome::default())));
+            continue;
+        };
+
+        let config = RunConfig {
+            report_file: output_path(&format!("run_report-{}.json", group_jid.user)),
+            ..run_config()
+        };
+        let outcome = add_to_group(client, group_jid, participant_jids, &config).await;
+        summary.push((group_input.clone(), Some(outcome)));
+        total.merge(outcome);
+
+        if outcome.account_restricted {
+            eprintln!("⛔ Account restricted, not processing the remaining groups.");
+            break;
+        }
+        if outcome.too_many_failures {
+            eprintln!("⛔ Too many consecutive failures, not processing the remaining groups.");
+            break;
+        }
+    }
+
+    say!("\n=== Per-group Summary ===");
+    for (group, outcome) in &summary {
+        match outcome {
+            Some(outcome) => say!(
+                "{}: {} added, {} skipped, {} failed",
+                group,
+                outcome.added,
+                outcome.skipped,
+                outcome.failed
+            ),
+            None => say!("{}: invalid group JID or invite link", group),
+        }
+    }
+
+    total
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    output::set_jsonl(CLI_ARGS.flag("jsonl"));
+    output::set_quiet(CLI_ARGS.flag("quiet"));
+    output::set_color(use_color());
+    groups::set_verbose_errors(CLI_ARGS.flag("verbose"));
+    groups::set_lid_fallback(CLI_ARGS.flag("lid-fallback") || SETTINGS.lid_fallback == Some(true));
+    if let Some(secs) = cli_value::<u64>("iq-timeout") {
+        groups::set_iq_timeout(std::time::Duration::from_secs(secs));
+    }
+
+    set_history_rotation(SETTINGS.history_max_kb.map(|max_kb| HistoryRotation {
+        max_bytes: max_kb * 1024,
+        compress: SETTINGS.compress_history.unwrap_or(false),
+    }));
+
+    if let Some(dir) = OUTPUT_DIR.as_deref()
+        && let Err(e) = set_output_dir(dir)
+    {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    lazy_static::initialize(&PROXY);
+
+    match CLI_ARGS.positional.first().map(String::as_str) {
+        Some("remove") => return run_command(Command::Remove).await,
+        Some("preflight") => return run_command(Command::Preflight).await,
+        Some("retry") => {
+            check_run_cooldown(CLI_ARGS.flag("force"));
+            return run_command(Command::Retry).await;
+        }
+        Some("groups") => return run_command(Command::Groups).await,
+        Some("invite-link") => return run_command(Command::InviteLink).await,
+        Some("diff") => return run_command(Command::Diff).await,
+        Some("watch") => return run_command(Command::Watch).await,
+        Some("demote-all-except") => return run_command(Command::DemoteAllExcept).await,
+        Some("ensure") => return run_command(Command::Ensure).await,
+        Some("broadcast") => return run_command(Command::Broadcast).await,
+        Some("clean") => return run_command(Command::Clean).await,
+        Some("reset") => std::process::exit(run_reset()),
+        Some("init") => std::process::exit(run_init()),
+        Some("accounts") => std::process::exit(run_accounts()),
+        Some("config") => std::process::exit(run_config_command()),
+        Some("export-session") => std::process::exit(run_export_session()),
+        Some("import-session") => std::process::exit(run_import_session()),
+        _ => {}
+    }
+
+    // The smoke test only adds the given number, so no phone list is needed
+    if CLI_ARGS.value("test-number").is_some() {
+        if *DRY_RUN {
+            eprintln!("Error: --test-number really adds the number and can't be a dry run");
+            std::process::exit(1);
+        }
+        return run_command(Command::TestNumber).await;
+    }
+
+    if CAMPAIGN.phones.is_none()
+        && CLI_ARGS.values("phones").is_empty()
+        && std::env::var_os(PHONES_ENV_VAR).is_none()
+        && !Path::new("phones.json").exists()
+    {
+        eprintln!(
+            "phones.json not found. Please create a JSON array of phone numbers (or set {}).",
+            PHONES_ENV_VAR
+        );
+        eprintln!("Run `init` to write a sample phones.json and message.txt.");
+        std::process::exit(1);
+    }
+
+    if CLI_ARGS.flag("estimate") {
+        print_estimate();
+        return Ok(());
+    }
+
+    // A per-group phones file names its own groups
+    if let PhoneList::Single(_) = *PHONES {
+        lazy_static::initialize(&INVITE_LINK);
+    }
+    check_run_cooldown(CLI_ARGS.flag("force"));
+
+    if let Some(image_path) = CLI_ARGS.value("invite-image")
+        && let Err(e) = validate_invite_image(image_path)
+    {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+    lazy_static::initialize(&WELCOME_MESSAGE);
+
+    if let Some(seed) = *SHUFFLE_SEED {
+        say!(
+            "🎲 Shuffle seed: {} (pass --shuffle-seed {} to replay this run)",
+            seed,
+            seed
+        );
+    }
+
+    if *DRY_RUN {
+        say!("=== Dry run: nothing will be sent ===");
+        say!("Delay between members: {}s", *DELAY_SECS);
+        say!("Message template: {}", *MESSAGE_FILE);
+        match &*PHONES {
+            PhoneList::Single(phone_numbers) => {
+                say!("Target: {}", *INVITE_LINK);
+                say!("Would process {} phone number(s):", phone_numbers.len());
+                for phone in phone_numbers {
+                    say!("  - {}", phone);
+                }
+            }
+            PhoneList::PerGroup(groups) => {
+                for (group, phone_numbers) in groups {
+                    say!(
+                        "Target: {} ({} phone number(s)):",
+                        group,
+                        phone_numbers.len()
+                    );
+                    for phone in phone_numbers {
+                        say!("  - {}", phone);
+                    }
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let backend = Arc::new(SqliteStore::new(&DB_PATH).await?);
+
+    let transport_factory = TokioWebSocketTransportFactory::new();
+    let http_client = http_client();
+
+    let mut bot = Bot::builder()
+        .with_backend(backend)
+        .with_transport_factory(transport_factory)
+        .with_http_client(http_client)
+        .on_event(|event, client| async move {
+            say!("{:?}", event);
+            match event {
+                Event::PairingQrCode { code, timeout } => on_pairing_qr(&code, timeout),
+                Event::LoggedOut(_) => on_logged_out(),
+                Event::Disconnected(_) => connection::mark_disconnected(),
+                Event::Connected(_) => {
+                    if !connection::mark_connected() {
+                        say!("Reconnected");
+                        return;
+                    }
+                    say!("Bot connected!");
+                    tokio::spawn(run_add(client));
+                }
+                _ => {}
+            }
+        })
+        .build()
+        .await?;
+
+    let bot_handle = bot.run().await?;
+    bot_handle.await?;
+    Ok(())
+}
+
+/// Subcommands that run once connected and exit with their own status code
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Remove,
+    Preflight,
+    Retry,
+    Groups,
+    InviteLink,
+    Diff,
+    Watch,
+    DemoteAllExcept,
+    Ensure,
+    Broadcast,
+    Clean,
+    TestNumber,
+}
+
+/// Connect and run a subcommand
+async fn run_command(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    let backend = Arc::new(SqliteStore::new(&DB_PATH).await?);
+
+    let mut bot = Bot::builder()
+        .with_backend(backend)
+        .with_transport_factory(TokioWebSocketTransportFactory::new())
+        .with_http_client(http_client())
+        .on_event(move |event, client| async move {
+            match event {
+                Event::PairingQrCode { .. } if matches!(command, Command::Preflight) => {
+                    print_check(
+                        false,
+                        "Session is not paired (run once to scan the QR code)",
+                    );
+                    std::process::exit(1);
+                }
+                Event::PairingQrCode { code, timeout } => on_pairing_qr(&code, timeout),
+                Event::LoggedOut(_) => on_logged_out(),
+                Event::Disconnected(_) => connection::mark_disconnected(),
+                Event::Connected(_) => {
+                    if !connection::mark_connected() {
+                        say!("Reconnected");
+                        return;
+                    }
+                    say!("Bot connected!");
+                    tokio::spawn(async move {
+                        let code = match command {
+                            Command::Remove => run_remove(&client).await,
+                            Command::Preflight => run_preflight(&client).await,
+                            Command::Retry => run_retry(&client).await,
+                            Command::Groups => run_groups(&client).await,
+                            Command::InviteLink => run_invite_link(&client).await,
+                            Command::Diff => run_diff(&client).await,
+                            Command::Watch => run_watch(&client).await,
+                            Command::DemoteAllExcept => run_demote_all_except(&client).await,
+                            Command::Ensure => run_ensure(&client).await,
+                            Command::Broadcast => run_broadcast(&client).await,
+                            Command::Clean => run_clean(&client).await,
+                            Command::TestNumber => run_test_number(&client).await,
+                        };
+                        std::process::exit(code);
+                    });
                 }
                 _ => {}
             }
@@ -1,17 +1,253 @@
-use crate::groups::GroupManagement;
+use crate::config::{InviteFormat, RetryPolicy, RiskThresholds, Role, RunConfig};
+use crate::connection;
+use crate::groups::{
+    self, AccountRestricted, GlobalRateLimit, GroupOps, InviteLink, MemberTtlRejected,
+};
+use crate::output::{self, emit_jsonl};
+use crate::pause;
+use crate::report::{RunMetrics, RunReport};
+use crate::{progress, say};
+use lazy_static::lazy_static;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
 use wacore_binary::jid::Jid;
 use waproto::whatsapp as wa;
-use whatsapp_rust::Client;
 
-#[derive(Debug)]
+lazy_static! {
+    /// Directory where generated files are written (empty = current directory)
+    static ref OUTPUT_DIR: Mutex<PathBuf> = Mutex::new(PathBuf::new());
+    /// When history files are archived (`None` = they grow forever)
+    static ref HISTORY_ROTATION: Mutex<Option<HistoryRotation>> = Mutex::new(None);
+}
+
+/// Size-based rotation of the history files (added_members.json, invalid_phones.json)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HistoryRotation {
+    /// Archive a history file once it is larger than this many bytes
+    pub max_bytes: u64,
+    /// Gzip-compress archived files
+    pub compress: bool,
+}
+
+/// Archive history files according to `rotation` before they are next written
+pub fn set_history_rotation(rotation: Option<HistoryRotation>) {
+    *HISTORY_ROTATION.lock().unwrap() = rotation;
+}
+
+/// Archive a history file if it outgrew the configured rotation size
+///
+/// Prints where the file was archived; errors are reported but don't prevent
+/// writing to the file.
+fn rotate_if_needed(path: &str) {
+    let Some(rotation) = *HISTORY_ROTATION.lock().unwrap() else {
+        return;
+    };
+
+    match rotate_history_file(path, rotation) {
+        Ok(Some(archive)) => say!("🗄️  Archived {} to {}", path, archive),
+        Ok(None) => {}
+        Err(e) => eprintln!("⚠️  Failed to rotate {}: {}", path, e),
+    }
+}
+
+/// Rename `path` with a timestamp suffix (gzip-compressed when configured) once it
+/// is larger than `rotation.max_bytes`, so the next write starts a fresh file
+///
+/// Returns the path of the archive, or `None` when no rotation was needed.
+/// Archives aren't read back: only the active file counts.
+pub fn rotate_history_file(
+    path: &str,
+    rotation: HistoryRotation,
+) -> Result<Option<String>, String> {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Ok(None);
+    };
+    if metadata.len() <= rotation.max_bytes {
+        return Ok(None);
+    }
+
+    let path_buf = Path::new(path);
+    let stem = path_buf
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("history");
+    let extension = path_buf
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("json");
+    let archive = path_buf
+        .with_file_name(format!(
+            "{}-{}.{}",
+            stem,
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            extension
+        ))
+        .to_string_lossy()
+        .into_owned();
+
+    fs::rename(path, &archive).map_err(|e| format!("Failed to rename to {}: {}", archive, e))?;
+    if !rotation.compress {
+        return Ok(Some(archive));
+    }
+
+    let compressed = format!("{}.gz", archive);
+    let gzip = || -> std::io::Result<()> {
+        let output = fs::File::create(&compressed)?;
+        let mut encoder = flate2::write::GzEncoder::new(output, flate2::Compression::default());
+        std::io::copy(&mut fs::File::open(&archive)?, &mut encoder)?;
+        encoder.finish()?;
+        fs::remove_file(&archive)
+    };
+    gzip().map_err(|e| format!("Failed to compress {}: {}", archive, e))?;
+    Ok(Some(compressed))
+}
+
+/// Write generated files (invalid_phones.json, run_report.json, ...) to `dir`
+pub fn set_output_dir(dir: &str) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e))?;
+    *OUTPUT_DIR.lock().unwrap() = PathBuf::from(dir);
+    Ok(())
+}
+
+/// Path of a generated file inside the output directory
+pub fn output_path(file_name: &str) -> String {
+    OUTPUT_DIR
+        .lock()
+        .unwrap()
+        .join(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Files generated in the output directory to carry state between runs
+const STATE_FILES: &[&str] = &[
+    "invalid_phones.json",
+    "invites_sent.json",
+    "failed_invites.json",
+    "retry_queue.json",
+    "added_members.json",
+    "last_run.json",
+    crate::report::DEFAULT_REPORT_FILE,
+];
+
+/// Prefixes of generated per-group state files (`<prefix><group>.json`)
+const PER_GROUP_STATE_PREFIXES: &[&str] = &["run_report-", "watch_progress-"];
+
+/// Generated state files currently present in the output directory
+///
+/// Includes the per-group run reports and watch progress files.
+pub fn existing_state_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = STATE_FILES
+        .iter()
+        .map(|file_name| PathBuf::from(output_path(file_name)))
+        .filter(|path| path.exists())
+        .collect();
+
+    let dir = OUTPUT_DIR.lock().unwrap().clone();
+    let dir = if dir.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        dir
+    };
+    if let Ok(entries) = fs::read_dir(&dir) {
+        let mut group_files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        PER_GROUP_STATE_PREFIXES
+                            .iter()
+                            .any(|prefix| name.starts_with(prefix))
+                            && name.ends_with(".json")
+                    })
+            })
+            .collect();
+        group_files.sort();
+        files.extend(group_files);
+    }
+
+    files
+}
+
+/// Upper bound for the delay between members after global rate limits
+const MAX_ADAPTIVE_DELAY_SECS: u64 = 300;
+
+/// Times a single member may wait for a reconnect before being counted as failed
+const MAX_RECONNECT_WAITS: usize = 3;
+
+/// How long a fetched group invite link is reused before querying it again
+pub const INVITE_LINK_TTL: Duration = Duration::from_secs(10 * 60);
+
+lazy_static! {
+    /// Names resolved during this run, keyed by JID (`None` = no name available)
+    static ref PUSH_NAME_CACHE: Mutex<HashMap<String, Option<String>>> =
+        Mutex::new(HashMap::new());
+    /// Invite links fetched during this run and when, keyed by group JID
+    static ref INVITE_LINK_CACHE: Mutex<HashMap<String, (std::time::Instant, InviteLink)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Outcome of adding one member, detailed enough to render without re-querying
+#[derive(Debug, Clone)]
 pub struct AddMemberResult {
     pub jid: Jid,
     pub success: bool,
     pub skipped: bool,
     pub should_send_invite: bool,
     pub should_track_invalid: bool,
+    /// The account itself is restricted from adding members; the run should stop
+    pub account_restricted: bool,
+    /// A request-wide rate limit was hit; the batch should slow down
+    pub global_rate_limited: bool,
+    /// Error code reported by the server for this member, if any
+    pub error_code: Option<u64>,
+    /// Human-readable description of the failure
+    pub error_text: Option<String>,
+    /// Display name of the member, when one could be resolved
+    pub name: Option<String>,
+    /// Times this member's add was retried after a rate limit (429)
+    pub rate_limit_retries: usize,
+    /// Time spent sleeping on rate-limit backoff for this member
+    pub rate_limit_wait: Duration,
+}
+
+impl AddMemberResult {
+    /// A result with nothing attempted yet
+    pub fn new(jid: Jid) -> Self {
+        Self {
+            jid,
+            success: false,
+            skipped: false,
+            should_send_invite: false,
+            should_track_invalid: false,
+            account_restricted: false,
+            global_rate_limited: false,
+            error_code: None,
+            error_text: None,
+            name: None,
+            rate_limit_retries: 0,
+            rate_limit_wait: Duration::ZERO,
+        }
+    }
+
+    /// Short status used in reports: "added", "skipped" or "failed"
+    pub fn status(&self) -> &'static str {
+        if self.success {
+            "added"
+        } else if self.skipped {
+            "skipped"
+        } else {
+            "failed"
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -19,98 +255,215 @@ pub struct AddMemberStats {
     pub total_success: usize,
     pub total_skipped: usize,
     pub total_failed: usize,
+    pub total_blocked: usize,
+    /// Members refused because the consent file doesn't list them
+    pub total_no_consent: usize,
+    /// Members left out because they're not in the `only_from` reference group
+    pub total_filtered: usize,
     pub invalid_phones: Vec<String>,
     pub failed_for_invite: Vec<Jid>,
+    /// Set when the run was aborted because the account is restricted
+    pub account_restricted: bool,
+    /// Set when the run was aborted because the account isn't an admin of the group
+    pub not_admin: bool,
+    /// Set when the run was aborted after `RunConfig::max_consecutive_failures` failures in a row
+    pub too_many_failures: bool,
+    /// The group creator, when the list contained them (skipped without an add)
+    pub skipped_creator: Option<Jid>,
+    /// Members not attempted because the run reached `RunConfig::max_duration`
+    pub total_remaining: usize,
+    /// Adds retried after a rate limit (429), across all members
+    pub rate_limit_retries: usize,
+    /// Total time spent sleeping on rate-limit backoff
+    pub rate_limit_wait: Duration,
+    /// Members added by this run, in order
+    pub added_jids: Vec<Jid>,
+    /// Members reported added but missing from the group afterwards (`RunConfig::verify_adds`)
+    pub unconfirmed_adds: Vec<Jid>,
+    /// Added members promoted to `RunConfig::assign_role`
+    pub total_promoted: usize,
+    /// Added members whose promotion failed
+    pub promote_failed: Vec<Jid>,
+    /// Per-member outcomes, written to the run report
+    pub report: Option<RunReport>,
+}
+
+/// Add one member to a group, retrying according to `policy`
+///
+/// This is the entry point for embedding the crate: it handles rate limits,
+/// timeouts and connection drops, logs progress, and returns everything needed
+/// to render the outcome (status, error code and text, member name).
+///
+/// # Example
+/// ```no_run
+/// use wacore_binary::jid::Jid;
+/// use whatsapp_invites::config::RetryPolicy;
+/// use whatsapp_invites::member_utils::add_one;
+/// use whatsapp_rust::Client;
+///
+/// async fn example(client: &Client, group_jid: &Jid) -> anyhow::Result<()> {
+///     let member_jid: Jid = "1234567890@s.whatsapp.net".parse()?;
+///     let result = add_one(client, group_jid, &member_jid, &RetryPolicy::default()).await;
+///     println!("{}: {}", result.jid, result.status());
+///     Ok(())
+/// }
+/// ```
+pub async fn add_one(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    member_jid: &Jid,
+    policy: &RetryPolicy,
+) -> AddMemberResult {
+    add_member_with_retry(client, group_jid, member_jid, policy).await
+}
+
+/// Describe a per-participant add error code
+fn describe_add_error(code: u64) -> &'static str {
+    match code {
+        400 => "Bad request (invalid phone number)",
+        403 => "Not authorized (you may not be an admin)",
+        404 => "User not found or doesn't have WhatsApp",
+        409 => "User is already in the group",
+        429 => "Rate limit exceeded (max retries reached)",
+        _ => "Unknown error code",
+    }
 }
 
 /// Add a single member with retry logic for rate limits
 pub async fn add_member_with_retry(
-    client: &Client,
+    client: &impl GroupOps,
     group_jid: &Jid,
     member_jid: &Jid,
-    max_retries: usize,
+    policy: &RetryPolicy,
 ) -> AddMemberResult {
+    let max_retries = policy.max_retries;
     let mut retry_count = 0;
-    let mut result = AddMemberResult {
-        jid: member_jid.clone(),
-        success: false,
-        skipped: false,
-        should_send_invite: false,
-        should_track_invalid: false,
-    };
+    let mut timeout_retried = false;
+    let mut reconnect_waits = 0;
+    let mut conflict_verified = false;
+    let mut result = AddMemberResult::new(member_jid.clone());
 
-    while retry_count <= max_retries {
+    'attempts: while retry_count <= max_retries {
         if retry_count > 0 {
-            println!("   Retry attempt {}/{}", retry_count, max_retries);
+            progress!("   Retry attempt {}/{}", retry_count, max_retries);
         }
 
-        match client
-            .add_group_participants(group_jid, std::slice::from_ref(member_jid))
-            .await
+        match send_add(
+            client,
+            group_jid,
+            std::slice::from_ref(member_jid),
+            policy.member_ttl,
+        )
+        .await
         {
             Ok(results) => {
                 for (jid, success, error_code) in results {
                     if success {
-                        println!("✓ Successfully added: {}", jid);
+                        result.name = cached_push_name(client, &jid).await;
+                        log_added(&jid, result.name.as_deref());
                         result.success = true;
                         return result;
                     } else {
                         if let Some(429) = error_code
                             && retry_count < max_retries
                         {
-                            println!("⚠️  Rate limited (429), waiting 30 seconds before retry...");
-                            tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+                            progress!(
+                                "⚠️  Rate limited for this member (429), retrying it in {} seconds...",
+                                policy.rate_limit_wait.as_secs()
+                            );
+                            tokio::time::sleep(policy.rate_limit_wait).await;
+                            result.rate_limit_retries += 1;
+                            result.rate_limit_wait += policy.rate_limit_wait;
                             retry_count += 1;
                             continue;
                         }
 
-                        if let Some(code) = error_code {
-                            result.should_track_invalid = code == 400;
-                            result.should_send_invite = code == 403 || code == 404;
-                            result.skipped = code == 409;
-
-                            if code == 409 {
-                                println!("⊘ Skipped: {} (already in group)", jid);
-                            } else {
-                                println!("✗ Failed to add: {} (error code: {:?})", jid, error_code);
+                        // A 409 can be stale when the member just left the group
+                        if let Some(409) = error_code
+                            && policy.verify_409
+                            && !conflict_verified
+                        {
+                            conflict_verified = true;
+                            if !is_group_participant(client, group_jid, &jid).await {
+                                progress!(
+                                    "⚠️  Got 409 but {} is not in the group, retrying once...",
+                                    jid
+                                );
+                                continue 'attempts;
                             }
+                        }
 
-                            match code {
-                                400 => println!(
-                                    "   → Bad request (invalid phone number - will be saved to invalid_phones.json)"
-                                ),
-                                403 => println!(
-                                    "   → Not authorized (you may not be an admin - will send invite message)"
-                                ),
-                                409 => println!("   → User is already in the group"),
-                                404 => println!(
-                                    "   → User not found or doesn't have WhatsApp (will send invite message)"
-                                ),
-                                429 => println!("   → Rate limit exceeded (max retries reached)"),
-                                _ => println!("   → Unknown error code"),
-                            }
+                        if let Some(code) = error_code {
+                            record_add_error(&mut result, &jid, code);
                         } else {
-                            println!("✗ Failed to add: {} (error code: {:?})", jid, error_code);
+                            progress!("✗ Failed to add: {} (error code: {:?})", jid, error_code);
                         }
                         return result;
                     }
                 }
             }
             Err(e) => {
+                if let Some(restriction) = e.downcast_ref::<AccountRestricted>() {
+                    eprintln!("✗ Failed to add {}: {}", member_jid, restriction);
+                    result.account_restricted = true;
+                    result.error_code = Some(restriction.code);
+                    result.error_text = Some(restriction.to_string());
+                    return result;
+                }
+
+                // Adding without the TTL would make a temporary member permanent
+                if let Some(rejection) = e.downcast_ref::<MemberTtlRejected>() {
+                    eprintln!(
+                        "✗ Not adding {}: {}; add it without --member-ttl",
+                        member_jid, rejection
+                    );
+                    result.error_code = Some(400);
+                    result.error_text = Some(rejection.to_string());
+                    return result;
+                }
+
                 let error_msg = e.to_string();
 
-                if (error_msg.contains("429") || error_msg.contains("rate-overlimit"))
-                    && retry_count < max_retries
-                {
-                    println!("⚠️  Rate limited, waiting 30 seconds before retry...");
-                    tokio::time::sleep(tokio::time::Duration::from_secs(30)).await;
+                if e.downcast_ref::<GlobalRateLimit>().is_some() && retry_count < max_retries {
+                    progress!(
+                        "⚠️  Global rate limit (adding too fast), pausing the batch for {} seconds...",
+                        policy.global_pause.as_secs()
+                    );
+                    result.global_rate_limited = true;
+                    tokio::time::sleep(policy.global_pause).await;
+                    result.rate_limit_retries += 1;
+                    result.rate_limit_wait += policy.global_pause;
                     retry_count += 1;
                     continue;
                 }
 
+                // Only a connection that is actually down is worth waiting for
+                if reconnect_waits < MAX_RECONNECT_WAITS && !client.is_connected().await {
+                    progress!("⚠️  Connection lost ({}), reconnecting...", error_msg);
+                    connection::mark_disconnected();
+                    reconnect_waits += 1;
+
+                    if connection::wait_for_reconnect(connection::RECONNECT_TIMEOUT).await {
+                        progress!("   Reconnected, resuming with {}", member_jid);
+                    } else {
+                        progress!(
+                            "   Still disconnected after {}s, retrying anyway...",
+                            connection::RECONNECT_TIMEOUT.as_secs()
+                        );
+                    }
+                    continue;
+                }
+
+                if is_timeout_error(&error_msg) && !timeout_retried {
+                    progress!("⚠️  Request timed out, retrying once...");
+                    timeout_retried = true;
+                    continue;
+                }
+
                 result.should_track_invalid =
                     error_msg.contains("400") || error_msg.contains("bad-request");
                 result.should_send_invite = error_msg.contains("403") || error_msg.contains("404");
+                result.error_text = Some(error_msg);
 
                 if result.should_track_invalid {
                     eprintln!(
@@ -131,247 +484,3108 @@ pub async fn add_member_with_retry(
     result
 }
 
-/// Extract phone number from JID
-pub fn jid_to_phone(jid: &Jid) -> String {
-    jid.to_string()
-        .replace("@s.whatsapp.net", "")
-        .replace("@lid", "")
+/// Log a successful add
+fn log_added(jid: &Jid, name: Option<&str>) {
+    progress!(
+        "{}",
+        output::green(&format!("✓ Added {}", display_member(jid, name)))
+    );
 }
 
-/// Save invalid phones to JSON file (appends without duplicates)
-pub fn save_invalid_phones(invalid_phones: &[String]) -> Result<usize, String> {
-    if invalid_phones.is_empty() {
-        return Ok(0);
+/// Record and log a per-participant add error code on `result`
+fn record_add_error(result: &mut AddMemberResult, jid: &Jid, code: u64) {
+    result.error_code = Some(code);
+    result.should_track_invalid = code == 400;
+    result.should_send_invite = code == 403 || code == 404;
+    result.skipped = code == 409;
+    result.error_text = Some(describe_add_error(code).to_string());
+
+    if code == 409 {
+        progress!("⊘ Skipped: {} (already in group)", jid);
+    } else {
+        progress!(
+            "{}",
+            output::red(&format!(
+                "✗ Failed to add: {} (error code: {:?})",
+                jid,
+                Some(code)
+            ))
+        );
     }
 
-    let file_path = "invalid_phones.json";
-    let mut all_invalid_phones: Vec<String> = Vec::new();
+    match code {
+        400 => progress!(
+            "   → {} - will be saved to invalid_phones.json",
+            describe_add_error(code)
+        ),
+        403 | 404 => progress!(
+            "   → {} - will send invite message",
+            describe_add_error(code)
+        ),
+        _ => progress!("   → {}", describe_add_error(code)),
+    }
+}
 
-    if Path::new(file_path).exists()
-        && let Ok(existing_data) = fs::read_to_string(file_path)
-        && let Ok(existing_phones) = serde_json::from_str::<Vec<String>>(&existing_data)
-    {
-        all_invalid_phones = existing_phones;
+/// Add error codes whose outcome is final, so a chunked add can use them as is
+const FINAL_ADD_ERROR_CODES: [u64; 4] = [400, 403, 404, 409];
+
+/// Add participants, with a membership TTL when one is set
+async fn send_add(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    participant_jids: &[Jid],
+    member_ttl: Option<Duration>,
+) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+    match member_ttl {
+        Some(ttl) => {
+            client
+                .add_group_participants_with_ttl(group_jid, participant_jids, ttl)
+                .await
+        }
+        None => {
+            client
+                .add_group_participants(group_jid, participant_jids)
+                .await
+        }
     }
+}
 
-    for phone in invalid_phones {
-        if !all_invalid_phones.contains(phone) {
-            all_invalid_phones.push(phone.clone());
+/// Send one add IQ for several members
+///
+/// Returns the outcome of each member whose answer is final (added, invalid,
+/// private, not on WhatsApp, already in the group), keyed by user. Members with
+/// any other answer, or all of them when the IQ fails, are left out so the caller
+/// adds them one by one with the usual retries.
+async fn add_chunk(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    chunk: &[Jid],
+    member_ttl: Option<Duration>,
+) -> HashMap<String, AddMemberResult> {
+    let mut outcomes = HashMap::new();
+
+    progress!(
+        "📦 Adding a chunk of {} members in one request",
+        chunk.len()
+    );
+    let results = match send_add(client, group_jid, chunk, member_ttl).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!(
+                "⚠️  Chunked add failed ({}), adding its members one by one",
+                e
+            );
+            return outcomes;
+        }
+    };
+
+    for (jid, success, error_code) in results {
+        let mut result = AddMemberResult::new(jid.clone());
+        if success {
+            result.name = cached_push_name(client, &jid).await;
+            log_added(&jid, result.name.as_deref());
+            result.success = true;
+        } else if let Some(code) = error_code
+            && FINAL_ADD_ERROR_CODES.contains(&code)
+        {
+            record_add_error(&mut result, &jid, code);
+        } else {
+            continue;
         }
+        outcomes.insert(jid.user.clone(), result);
     }
 
-    let json_data = serde_json::to_string_pretty(&all_invalid_phones)
-        .map_err(|e| format!("Failed to serialize: {}", e))?;
+    outcomes
+}
 
-    fs::write(file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+/// Whether a fresh participant check finds the member in the group
+///
+/// Assumes the member is present when the check fails.
+async fn is_group_participant(client: &impl GroupOps, group_jid: &Jid, member_jid: &Jid) -> bool {
+    match client.is_participant(group_jid, member_jid).await {
+        Ok(present) => present,
+        Err(e) => {
+            log::debug!("Could not re-check participants of {}: {}", group_jid, e);
+            true
+        }
+    }
+}
 
-    Ok(all_invalid_phones.len())
+/// Whether an IQ error message describes a timeout rather than a server rejection
+fn is_timeout_error(error_msg: &str) -> bool {
+    let lower = error_msg.to_lowercase();
+    lower.contains("timed out") || lower.contains("timeout")
 }
 
-/// Load list of phones that already received invite messages
-fn load_invites_sent() -> Vec<String> {
-    let file_path = "invites_sent.json";
-    if Path::new(file_path).exists()
-        && let Ok(data) = fs::read_to_string(file_path)
-        && let Ok(phones) = serde_json::from_str::<Vec<String>>(&data)
+/// Resolve a member's name, querying the server at most once per JID per run
+async fn cached_push_name(client: &impl GroupOps, jid: &Jid) -> Option<String> {
+    let key = jid.to_string();
+    if let Some(name) = PUSH_NAME_CACHE.lock().unwrap().get(&key) {
+        return name.clone();
+    }
+
+    let name = match client.resolve_push_name(jid).await {
+        Ok(name) => name,
+        Err(e) => {
+            log::debug!("Failed to resolve name of {}: {}", jid, e);
+            None
+        }
+    };
+
+    PUSH_NAME_CACHE.lock().unwrap().insert(key, name.clone());
+    name
+}
+
+/// Get a group's invite link, reusing the one fetched less than `INVITE_LINK_TTL` ago
+///
+/// Failures aren't cached, so the next call queries the server again.
+pub async fn cached_invite_link(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+) -> anyhow::Result<InviteLink> {
+    let key = group_jid.to_string();
+    if let Some((fetched_at, link)) = INVITE_LINK_CACHE.lock().unwrap().get(&key)
+        && fetched_at.elapsed() < INVITE_LINK_TTL
     {
-        return phones;
+        return Ok(link.clone());
     }
-    Vec::new()
+
+    let link = client.get_group_invite_link(group_jid).await?;
+    INVITE_LINK_CACHE
+        .lock()
+        .unwrap()
+        .insert(key, (std::time::Instant::now(), link.clone()));
+    Ok(link)
 }
 
-/// Save list of phones that received invite messages
-fn save_invites_sent(phones: &[String]) -> Result<(), String> {
-    let file_path = "invites_sent.json";
-    let json_data =
-        serde_json::to_string_pretty(phones).map_err(|e| format!("Failed to serialize: {}", e))?;
+/// Drop the cached invite link of a group, e.g. once the link was revoked
+pub fn forget_invite_link(group_jid: &Jid) {
+    INVITE_LINK_CACHE
+        .lock()
+        .unwrap()
+        .remove(&group_jid.to_string());
+}
+
+/// Format a member for logs: "+1234567890 (Alice)", or "+1234567890" without a name
+///
+/// `@lid` members are shown as their JID, since a LID isn't a phone number.
+fn display_member(jid: &Jid, name: Option<&str>) -> String {
+    let member = if jid.server == "lid" {
+        jid.to_string()
+    } else {
+        format!("+{}", jid_to_phone(jid))
+    };
 
-    fs::write(file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+    match name {
+        Some(name) => format!("{} ({})", member, name),
+        None => member,
+    }
+}
 
-    Ok(())
+/// Extract phone number from JID
+///
+/// Drops the server as well as any multi-device suffix (`:12`) and agent form
+/// (`.0`), so `1234567890.0:12@s.whatsapp.net` gives `1234567890`.
+pub fn jid_to_phone(jid: &Jid) -> String {
+    let jid = jid.to_string();
+    let user = jid.split('@').next().unwrap_or_default();
+    let user = user.split(':').next().unwrap_or_default();
+    user.split('.').next().unwrap_or_default().to_string()
 }
 
-/// Load invite message template from message.txt
-/// Returns default template if file doesn't exist
-fn load_invite_message_template() -> String {
-    let file_path = "message.txt";
+/// Normalize a raw phone number to digits only (drops `+`, spaces, dashes, etc.)
+pub fn normalize_phone(raw: &str) -> String {
+    raw.chars().filter(|c| c.is_ascii_digit()).collect()
+}
 
-    if Path::new(file_path).exists()
-        && let Ok(template) = fs::read_to_string(file_path)
+/// Read the phone numbers of a CSV file: the first column of every row
+///
+/// A first row without any digit is taken as a header and skipped, as are empty
+/// rows. Fields may be double-quoted.
+pub fn parse_phones_csv(data: &str) -> Vec<String> {
+    let mut lines = data.lines().peekable();
+    if lines
+        .peek()
+        .is_some_and(|header| !header.chars().any(|c| c.is_ascii_digit()))
     {
-        return template.trim().to_string();
+        lines.next();
     }
 
-    // Default template if file doesn't exist
-    "Hi! You've been invited to join our WhatsApp group.\n\n\
-     Join here: {link}\n\n\
-     If the link doesn't work, please contact an admin."
-        .to_string()
+    lines
+        .filter_map(|line| {
+            let field = line.split(',').next()?.trim().trim_matches('"').trim();
+            (!field.is_empty()).then(|| field.to_string())
+        })
+        .collect()
 }
 
-/// Send invite messages to members who couldn't be added
-pub async fn send_invite_messages(client: &Client, group_jid: &Jid, failed_jids: &[Jid]) -> usize {
-    if failed_jids.is_empty() {
-        return 0;
-    }
+/// The first key listed twice in a JSON object, `None` for anything else
+///
+/// A per-group phones file is read into a map, which would otherwise keep only the
+/// last list of a group listed twice.
+pub fn duplicate_json_key(data: &str) -> Option<String> {
+    struct FirstDuplicate(Option<String>);
 
-    // Load list of phones that already received invites
-    let mut invites_sent = load_invites_sent();
+    struct FirstDuplicateVisitor;
 
-    // Filter out JIDs that already received invite messages
-    let mut pending_jids = Vec::new();
-    let mut skipped_count = 0;
+    impl<'de> serde::de::Visitor<'de> for FirstDuplicateVisitor {
+        type Value = FirstDuplicate;
 
-    for jid in failed_jids {
-        let phone = jid_to_phone(jid);
-        if invites_sent.contains(&phone) {
-            println!("⊘ Skipped invite to {} (already sent)", jid);
-            skipped_count += 1;
-        } else {
-            pending_jids.push(jid.clone());
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a JSON object")
+        }
+
+        fn visit_map<A: serde::de::MapAccess<'de>>(
+            self,
+            mut map: A,
+        ) -> Result<Self::Value, A::Error> {
+            let mut seen = HashSet::new();
+            let mut duplicate = None;
+            while let Some(key) = map.next_key::<String>()? {
+                map.next_value::<serde::de::IgnoredAny>()?;
+                if duplicate.is_none() && !seen.insert(key.clone()) {
+                    duplicate = Some(key);
+                }
+            }
+            Ok(FirstDuplicate(duplicate))
         }
     }
 
-    if pending_jids.is_empty() {
-        if skipped_count > 0 {
-            println!(
-                "\n✓ All {} member(s) already received invite messages",
-                skipped_count
-            );
+    impl<'de> serde::Deserialize<'de> for FirstDuplicate {
+        fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_map(FirstDuplicateVisitor)
         }
-        return 0;
     }
 
-    println!("\n=== Sending Invite Messages ===");
-    if skipped_count > 0 {
-        println!(
-            "Sending invite messages to {} new members ({} already sent)\n",
-            pending_jids.len(),
-            skipped_count
-        );
+    serde_json::from_str::<FirstDuplicate>(data)
+        .ok()
+        .and_then(|duplicate| duplicate.0)
+}
+
+/// Key identifying a member entry across lists: its digits, or the JID as written
+pub fn member_entry_key(entry: &str) -> String {
+    if entry.contains('@') {
+        entry.trim().to_string()
     } else {
-        println!(
-            "Sending invite messages to {} members who couldn't be added directly\n",
-            pending_jids.len()
-        );
+        normalize_phone(entry)
     }
+}
 
-    // Try to get the group invite link
-    let invite_link = match client.get_group_invite_link(group_jid).await {
-        Ok(link) => link,
-        Err(e) => {
-            eprintln!("⚠️  Failed to get group invite link: {}", e);
-            "(ask admin for invite link)".to_string()
-        }
-    };
+/// Servers a member JID may use: phone-number JIDs and LIDs
+const MEMBER_JID_SERVERS: [&str; 2] = ["s.whatsapp.net", "lid"];
 
-    // Load message template and interpolate the invite link
-    let template = load_invite_message_template();
-    let invite_message = template.replace("{link}", &invite_link);
+/// Build the JID of a member from an input list entry
+///
+/// Bare phone numbers become `<digits>@s.whatsapp.net`; entries containing `@`
+/// are full JIDs (e.g. `123456789012345@lid` for contacts only known by their
+/// LID) and are parsed as-is. Either way the server must be a user server.
+pub fn parse_member_jid(input: &str) -> Result<Jid, String> {
+    let input = input.trim();
+    let jid: Jid = if input.contains('@') {
+        input.parse()
+    } else {
+        format!("{}@s.whatsapp.net", input).parse()
+    }
+    .map_err(|_| format!("Invalid phone number or JID: {}", input))?;
 
-    let mut sent_count = 0;
+    if !MEMBER_JID_SERVERS.contains(&jid.server.as_str()) {
+        return Err(format!(
+            "Unsupported JID server for a member: {} (expected @s.whatsapp.net or @lid)",
+            input
+        ));
+    }
 
-    for jid in &pending_jids {
-        let message = wa::Message {
-            conversation: Some(invite_message.clone()),
-            ..Default::default()
-        };
+    Ok(jid)
+}
 
-        match client.send_message(jid.clone(), message).await {
-            Ok(_) => {
-                println!("📧 Sent invite message to {}", jid);
+/// Fewest digits a phone number can have, country code included
+const MIN_PHONE_DIGITS: usize = 7;
 
-                // Track that invite was sent
-                let phone = jid_to_phone(jid);
-                if !invites_sent.contains(&phone) {
-                    invites_sent.push(phone);
-                }
+/// Convert an input list to member JIDs, keeping why each rejected entry failed
+///
+/// Entries are bare phone numbers (`+`, spaces, dashes, dots and parentheses are
+/// allowed as formatting) or full JIDs. With `country_code`, numbers written with
+/// a leading `0` are made international first. Entries starting with `//` are
+/// notes and are left out.
+///
+/// # Returns
+/// The valid JIDs, and `(raw entry, reason)` for every rejected entry. The reason
+/// is "not numeric", "too short", "invalid number" or "JID parse error".
+pub fn convert_member_list(
+    entries: &[String],
+    country_code: Option<&str>,
+) -> (Vec<Jid>, Vec<(String, String)>) {
+    let mut jids = Vec::new();
+    let mut rejected = Vec::new();
 
-                sent_count += 1;
-            }
-            Err(e) => eprintln!("⚠️  Failed to send message to {}: {}", jid, e),
+    for raw in entries {
+        if raw.trim_start().starts_with("//") {
+            continue;
+        }
+        match convert_member_entry(raw, country_code) {
+            Ok(jid) => jids.push(jid),
+            Err(reason) => rejected.push((raw.clone(), reason.to_string())),
         }
+    }
+
+    (jids, rejected)
+}
 
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+/// Convert one input entry, see `convert_member_list`
+fn convert_member_entry(raw: &str, country_code: Option<&str>) -> Result<Jid, &'static str> {
+    let raw = raw.trim();
+    if raw.contains('@') {
+        return parse_member_jid(raw).map_err(|_| "JID parse error");
     }
 
-    // Save updated list of invites sent
-    if sent_count > 0
-        && let Err(e) = save_invites_sent(&invites_sent)
-    {
-        eprintln!("⚠️  Failed to save invites_sent.json: {}", e);
+    let is_numeric = raw.chars().any(|c| c.is_ascii_digit())
+        && raw
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '(' | ')' | '.' | ' '));
+    if !is_numeric {
+        return Err("not numeric");
+    }
+
+    let phone = match country_code {
+        Some(country_code) => apply_country_code(raw, country_code),
+        None => normalize_phone(raw),
+    };
+    if phone.len() < MIN_PHONE_DIGITS {
+        return Err("too short");
     }
+    let phone = canonical_phone(&phone)?;
 
-    sent_count
+    parse_member_jid(&phone).map_err(|_| "JID parse error")
 }
 
-/// Process adding multiple members one by one with delays
-pub async fn add_members_batch(
-    client: &Client,
-    group_jid: &Jid,
-    member_jids: &[Jid],
-    delay_seconds: u64,
-) -> AddMemberStats {
-    let mut stats = AddMemberStats::default();
+/// Check an international number (digits only) against its country's numbering plan
+///
+/// Returns the canonical E.164 digits, without the `+`.
+#[cfg(feature = "e164")]
+fn canonical_phone(phone: &str) -> Result<String, &'static str> {
+    let number = phonenumber::parse(None, format!("+{}", phone)).map_err(|_| "invalid number")?;
+    if !phonenumber::is_valid(&number) {
+        return Err("invalid number");
+    }
 
-    println!(
-        "Adding {} members one by one ({}s delay between each)...\n",
-        member_jids.len(),
-        delay_seconds
-    );
+    Ok(normalize_phone(
+        &number.format().mode(phonenumber::Mode::E164).to_string(),
+    ))
+}
 
-    for (index, jid) in member_jids.iter().enumerate() {
-        println!("=== Adding member {}/{} ===", index + 1, member_jids.len());
+/// Without the `e164` feature, any number long enough is accepted as is
+#[cfg(not(feature = "e164"))]
+fn canonical_phone(phone: &str) -> Result<String, &'static str> {
+    Ok(phone.to_string())
+}
 
-        let result = add_member_with_retry(client, group_jid, jid, 2).await;
+/// Turn a locally-formatted number into an international one
+///
+/// Numbers written with a leading `+` or `00` are already international; a single
+/// leading `0` is a national trunk prefix and is replaced by `country_code`.
+pub fn apply_country_code(raw: &str, country_code: &str) -> String {
+    let raw = raw.trim();
+    let digits = normalize_phone(raw);
 
-        if result.success {
-            stats.total_success += 1;
-        } else if result.skipped {
-            stats.total_skipped += 1;
-        } else {
-            stats.total_failed += 1;
+    if raw.starts_with('+') {
+        return digits;
+    }
+    if let Some(international) = digits.strip_prefix("00") {
+        return international.to_string();
+    }
 
-            if result.should_track_invalid {
-                let phone = jid_to_phone(jid);
-                stats.invalid_phones.push(phone);
-            }
+    match digits.strip_prefix('0') {
+        Some(national) => format!("{}{}", normalize_phone(country_code), national),
+        None => digits,
+    }
+}
 
-            if result.should_send_invite {
-                stats.failed_for_invite.push(jid.clone());
-            }
-        }
+/// Load blocklisted phones from blocklist.json (normalized)
+/// Returns an empty list if the file doesn't exist
+pub fn load_blocklist() -> Vec<String> {
+    let file_path = "blocklist.json";
+    if !Path::new(file_path).exists() {
+        return Vec::new();
+    }
 
-        if index < member_jids.len() - 1 {
-            println!("Waiting {} seconds before next member...\n", delay_seconds);
-            tokio::time::sleep(tokio::time::Duration::from_secs(delay_seconds)).await;
+    match fs::read_to_string(file_path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_str::<Vec<String>>(&data).map_err(|e| e.to_string()))
+    {
+        Ok(phones) => phones.iter().map(|phone| normalize_phone(phone)).collect(),
+        Err(e) => {
+            eprintln!("⚠️  Failed to load blocklist.json: {}", e);
+            Vec::new()
         }
     }
-
-    stats
 }
 
-pub async fn finalize_member_addition(client: &Client, group_jid: &Jid, stats: AddMemberStats) {
-    println!("\n=== Final Summary ===");
-    println!("✓ Successfully added: {}", stats.total_success);
-    println!("⊘ Skipped: {}", stats.total_skipped);
-    println!("✗ Failed: {}", stats.total_failed);
-    println!(
-        "Total processed: {}",
-        stats.total_success + stats.total_skipped + stats.total_failed
-    );
+/// Load the consent file: the normalized phones that agreed to be added
+///
+/// Reads a JSON array of phone numbers, or a CSV file (by extension) with the
+/// phones in its first column. Unlike the blocklist, a missing file is an error.
+pub fn load_consent_file(file_path: &str) -> Result<HashSet<String>, String> {
+    let data = fs::read_to_string(file_path)
+        .map_err(|e| format!("Failed to read consent file {}: {}", file_path, e))?;
 
-    if !stats.failed_for_invite.is_empty() {
-        send_invite_messages(client, group_jid, &stats.failed_for_invite).await;
+    let phones = if file_path.to_lowercase().ends_with(".csv") {
+        parse_phones_csv(&data)
+    } else {
+        serde_json::from_str::<Vec<String>>(&data)
+            .map_err(|e| format!("Failed to parse consent file {}: {}", file_path, e))?
+    };
+
+    Ok(phones.iter().map(|phone| normalize_phone(phone)).collect())
+}
+
+/// Save invalid phones to JSON file (appends without duplicates)
+pub fn save_invalid_phones(invalid_phones: &[String]) -> Result<usize, String> {
+    if invalid_phones.is_empty() {
+        return Ok(0);
     }
 
-    if !stats.invalid_phones.is_empty() {
-        match save_invalid_phones(&stats.invalid_phones) {
-            Ok(total) => println!(
+    let file_path = output_path("invalid_phones.json");
+    rotate_if_needed(&file_path);
+    let mut all_invalid_phones: Vec<String> = Vec::new();
+
+    if Path::new(&file_path).exists()
+        && let Ok(existing_data) = fs::read_to_string(&file_path)
+        && let Ok(existing_phones) = serde_json::from_str::<Vec<String>>(&existing_data)
+    {
+        all_invalid_phones = existing_phones;
+    }
+
+    for phone in invalid_phones {
+        if !all_invalid_phones.contains(phone) {
+            all_invalid_phones.push(phone.clone());
+        }
+    }
+
+    let json_data = serde_json::to_string_pretty(&all_invalid_phones)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(all_invalid_phones.len())
+}
+
+/// Record members whose invite message couldn't be sent in failed_invites.json
+///
+/// Members invited successfully since are dropped from the file, so it only lists
+/// phones still waiting for an invite. Returns the number of phones in the file.
+pub fn save_failed_invites(invite_stats: &InviteSendStats) -> Result<usize, String> {
+    let file_path = output_path("failed_invites.json");
+    let mut failed_invites: Vec<String> = Vec::new();
+
+    if Path::new(&file_path).exists()
+        && let Ok(existing_data) = fs::read_to_string(&file_path)
+        && let Ok(existing_phones) = serde_json::from_str::<Vec<String>>(&existing_data)
+    {
+        failed_invites = existing_phones;
+    }
+
+    let sent: Vec<String> = invite_stats
+        .sent
+        .iter()
+        .map(|jid| normalize_phone(&jid_to_phone(jid)))
+        .collect();
+    failed_invites.retain(|phone| !sent.contains(phone));
+
+    for (jid, _) in &invite_stats.failed {
+        let phone = normalize_phone(&jid_to_phone(jid));
+        if !failed_invites.contains(&phone) {
+            failed_invites.push(phone);
+        }
+    }
+
+    let json_data = serde_json::to_string_pretty(&failed_invites)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(failed_invites.len())
+}
+
+/// Current UTC time as an RFC 3339 timestamp
+pub fn now_timestamp() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Append successfully added members to added_members.json (keeps full history)
+pub fn save_added_members(
+    group_jid: &Jid,
+    added_jids: &[Jid],
+    label: &str,
+) -> Result<usize, String> {
+    if added_jids.is_empty() {
+        return Ok(0);
+    }
+
+    let file_path = output_path("added_members.json");
+    rotate_if_needed(&file_path);
+    let mut records: Vec<serde_json::Value> = Vec::new();
+
+    if Path::new(&file_path).exists() {
+        let existing_data =
+            fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+        records = serde_json::from_str(&existing_data)
+            .map_err(|e| format!("Failed to parse existing records: {}", e))?;
+    }
+
+    let timestamp = now_timestamp();
+    for jid in added_jids {
+        records.push(serde_json::json!({
+            "jid": jid.to_string(),
+            "phone": jid_to_phone(jid),
+            "timestamp": timestamp,
+            "group": group_jid.to_string(),
+            "label": label,
+        }));
+    }
+
+    let json_data = serde_json::to_string_pretty(&records)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(records.len())
+}
+
+/// Append one JSON line describing an add attempt to the audit log at `path`
+///
+/// The log is append-only and spans runs, unlike the run report. Every attempt is
+/// logged, whatever its outcome.
+pub fn append_audit_event(
+    path: &str,
+    group_jid: &Jid,
+    result: &AddMemberResult,
+) -> Result<(), String> {
+    let event = serde_json::json!({
+        "ts": now_timestamp(),
+        "group": group_jid.to_string(),
+        "jid": result.jid.to_string(),
+        "action": "add",
+        "result": result.status(),
+        "error_code": result.error_code,
+    });
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    std::io::Write::write_all(&mut file, format!("{}\n", event).as_bytes())
+        .map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Phones recorded in added_members.json as added to this group
+pub fn load_previously_added(group_jid: &Jid) -> Vec<String> {
+    let Ok(data) = fs::read_to_string(output_path("added_members.json")) else {
+        return Vec::new();
+    };
+    let Ok(records) = serde_json::from_str::<Vec<serde_json::Value>>(&data) else {
+        eprintln!("⚠️  Failed to parse added_members.json, not skipping previously added members");
+        return Vec::new();
+    };
+
+    let group = group_jid.to_string();
+    records
+        .iter()
+        .filter(|record| record.get("group").and_then(|g| g.as_str()) == Some(group.as_str()))
+        .filter_map(|record| record.get("phone").and_then(|phone| phone.as_str()))
+        .map(normalize_phone)
+        .collect()
+}
+
+/// Members of a group queued in retry_queue.json by earlier runs
+pub fn load_retry_queue(group_jid: &Jid) -> Vec<Jid> {
+    let Ok(data) = fs::read_to_string(output_path("retry_queue.json")) else {
+        return Vec::new();
+    };
+    let Ok(records) = serde_json::from_str::<Vec<serde_json::Value>>(&data) else {
+        eprintln!("⚠️  Failed to parse retry_queue.json, ignoring it");
+        return Vec::new();
+    };
+
+    let group = group_jid.to_string();
+    records
+        .iter()
+        .filter(|record| record.get("group").and_then(|g| g.as_str()) == Some(group.as_str()))
+        .filter_map(|record| record.get("jid")?.as_str()?.parse().ok())
+        .collect()
+}
+
+/// Sync retry_queue.json with the outcome of a run
+///
+/// Members that failed for a retryable reason (rate limit, timeout, network) are
+/// queued; refusals such as 403/401/423 are not, and every other member processed
+/// by the run leaves the queue. Returns the
+/// number of members queued for the group.
+pub fn update_retry_queue(group_jid: &Jid, report: &RunReport) -> Result<usize, String> {
+    let file_path = output_path("retry_queue.json");
+    let mut records: Vec<serde_json::Value> = Vec::new();
+
+    if Path::new(&file_path).exists() {
+        let existing_data =
+            fs::read_to_string(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+        records = serde_json::from_str(&existing_data)
+            .map_err(|e| format!("Failed to parse existing records: {}", e))?;
+    }
+
+    let group = group_jid.to_string();
+    let processed: Vec<&str> = report
+        .entries
+        .iter()
+        .map(|entry| entry.jid.as_str())
+        .collect();
+    records.retain(|record| {
+        record.get("group").and_then(|g| g.as_str()) != Some(group.as_str())
+            || !record
+                .get("jid")
+                .and_then(|jid| jid.as_str())
+                .is_some_and(|jid| processed.contains(&jid))
+    });
+
+    let timestamp = now_timestamp();
+    for entry in report.entries.iter().filter(|entry| entry.is_retryable()) {
+        records.push(serde_json::json!({
+            "jid": entry.jid,
+            "group": group,
+            "error_code": entry.error_code,
+            "timestamp": timestamp,
+        }));
+    }
+
+    let json_data = serde_json::to_string_pretty(&records)
+        .map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(records
+        .iter()
+        .filter(|record| record.get("group").and_then(|g| g.as_str()) == Some(group.as_str()))
+        .count())
+}
+
+/// Load the time the previous batch finished from last_run.json
+pub fn load_last_run() -> Option<chrono::DateTime<chrono::Utc>> {
+    let data = fs::read_to_string(output_path("last_run.json")).ok()?;
+    let value: serde_json::Value = serde_json::from_str(&data).ok()?;
+    let finished_at = value.get("finished_at")?.as_str()?;
+
+    chrono::DateTime::parse_from_rfc3339(finished_at)
+        .ok()
+        .map(|time| time.with_timezone(&chrono::Utc))
+}
+
+/// Record that a batch just finished in last_run.json
+pub fn save_last_run() -> Result<(), String> {
+    let json_data = serde_json::to_string_pretty(&serde_json::json!({
+        "finished_at": now_timestamp(),
+    }))
+    .map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(output_path("last_run.json"), json_data)
+        .map_err(|e| format!("Failed to write file: {}", e))
+}
+
+/// Load list of phones that already received invite messages
+///
+/// Entries are normalized; a file holding non-normalized entries (e.g. with `+`)
+/// is rewritten once in normalized form.
+fn load_invites_sent() -> Vec<String> {
+    let file_path = output_path("invites_sent.json");
+    if Path::new(&file_path).exists()
+        && let Ok(data) = fs::read_to_string(&file_path)
+        && let Ok(phones) = serde_json::from_str::<Vec<String>>(&data)
+    {
+        let mut normalized: Vec<String> = Vec::new();
+        for phone in &phones {
+            let phone = normalize_phone(phone);
+            if !normalized.contains(&phone) {
+                normalized.push(phone);
+            }
+        }
+
+        if normalized != phones
+            && let Err(e) = save_invites_sent(&normalized)
+        {
+            eprintln!("⚠️  Failed to migrate invites_sent.json: {}", e);
+        }
+
+        return normalized;
+    }
+    Vec::new()
+}
+
+/// Save list of phones that received invite messages
+fn save_invites_sent(phones: &[String]) -> Result<(), String> {
+    let file_path = output_path("invites_sent.json");
+    let json_data =
+        serde_json::to_string_pretty(phones).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(())
+}
+
+/// Path of the watch progress file of a group
+fn watch_progress_path(group_jid: &Jid) -> String {
+    output_path(&format!("watch_progress-{}.json", group_jid.user))
+}
+
+/// Load the entries of a watched phones file already processed for a group
+pub fn load_watch_progress(group_jid: &Jid) -> Vec<String> {
+    let file_path = watch_progress_path(group_jid);
+    if Path::new(&file_path).exists()
+        && let Ok(data) = fs::read_to_string(&file_path)
+        && let Ok(entries) = serde_json::from_str::<Vec<String>>(&data)
+    {
+        return entries;
+    }
+    Vec::new()
+}
+
+/// Save the entries of a watched phones file already processed for a group
+pub fn save_watch_progress(group_jid: &Jid, entries: &[String]) -> Result<(), String> {
+    let file_path = watch_progress_path(group_jid);
+    let json_data =
+        serde_json::to_string_pretty(entries).map_err(|e| format!("Failed to serialize: {}", e))?;
+
+    fs::write(&file_path, json_data).map_err(|e| format!("Failed to write file: {}", e))?;
+
+    Ok(())
+}
+
+/// Settings stored in the front-matter of a message file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MessageSettings {
+    pub delay_secs: Option<u64>,
+    pub invite_format: Option<InviteFormat>,
+}
+
+/// Split a message file into its front-matter settings and its template
+///
+/// The front-matter is an optional block of `key: value` lines between two `---`
+/// lines at the top of the file. Files without it are a plain template. Unknown
+/// keys and invalid values are reported and ignored.
+fn parse_front_matter(contents: &str) -> (MessageSettings, String) {
+    let mut settings = MessageSettings::default();
+    let mut lines = contents.lines();
+
+    if lines.next().map(str::trim) != Some("---") {
+        return (settings, contents.to_string());
+    }
+
+    let mut header = Vec::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim() == "---" {
+            closed = true;
+            break;
+        }
+        header.push(line);
+    }
+
+    // An unclosed block is just text starting with a horizontal rule
+    if !closed {
+        return (settings, contents.to_string());
+    }
+
+    for line in header {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            eprintln!("⚠️  Ignoring front-matter line without a key: {}", line);
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+
+        match key {
+            "delay_secs" => match value.parse() {
+                Ok(delay_secs) => settings.delay_secs = Some(delay_secs),
+                Err(_) => eprintln!("⚠️  Ignoring invalid delay_secs: {}", value),
+            },
+            "invite_format" => match value.parse() {
+                Ok(invite_format) => settings.invite_format = Some(invite_format),
+                Err(e) => eprintln!("⚠️  Ignoring invalid invite_format {}: {}", value, e),
+            },
+            _ => eprintln!("⚠️  Ignoring unknown front-matter key: {}", key),
+        }
+    }
+
+    (settings, lines.collect::<Vec<_>>().join("\n"))
+}
+
+/// Built-in invite templates by language code, used when there's no message file
+const DEFAULT_INVITE_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "en",
+        "Hi! You've been invited to join our WhatsApp group.\n\n\
+         Join here: {link}\n\n\
+         If the link doesn't work, please contact an admin.",
+    ),
+    (
+        "fr",
+        "Bonjour ! Vous avez été invité(e) à rejoindre notre groupe WhatsApp.\n\n\
+         Rejoignez-le ici : {link}\n\n\
+         Si le lien ne fonctionne pas, contactez un administrateur.",
+    ),
+    (
+        "es",
+        "¡Hola! Te han invitado a unirte a nuestro grupo de WhatsApp.\n\n\
+         Únete aquí: {link}\n\n\
+         Si el enlace no funciona, contacta con un administrador.",
+    ),
+    (
+        "ar",
+        "مرحبًا! تمت دعوتك للانضمام إلى مجموعتنا على واتساب.\n\n\
+         انضم من هنا: {link}\n\n\
+         إذا لم يعمل الرابط، يرجى التواصل مع أحد المشرفين.",
+    ),
+    (
+        "pt",
+        "Olá! Você foi convidado para entrar no nosso grupo do WhatsApp.\n\n\
+         Entre aqui: {link}\n\n\
+         Se o link não funcionar, fale com um administrador.",
+    ),
+];
+
+/// The built-in invite template for a language code such as `fr` or `pt-BR`
+///
+/// Returns `None` for languages without a built-in template.
+pub fn default_invite_template(lang: &str) -> Option<&'static str> {
+    let code = lang.split(['-', '_']).next().unwrap_or_default();
+    DEFAULT_INVITE_TEMPLATES
+        .iter()
+        .find(|(template_lang, _)| template_lang.eq_ignore_ascii_case(code))
+        .map(|(_, template)| *template)
+}
+
+/// Load invite message template from the given file (usually message.txt)
+///
+/// Returns the settings from the file's front-matter (if any) and the template.
+/// Returns the built-in template for `lang` (English for unknown languages) if
+/// the file doesn't exist.
+pub fn load_invite_message_template(file_path: &str, lang: &str) -> (MessageSettings, String) {
+    if Path::new(file_path).exists()
+        && let Ok(contents) = fs::read_to_string(file_path)
+    {
+        let (settings, template) = parse_front_matter(&contents);
+        return (settings, template.trim().to_string());
+    }
+
+    let template = default_invite_template(lang)
+        .unwrap_or(DEFAULT_INVITE_TEMPLATES[0].1)
+        .to_string();
+    (MessageSettings::default(), template)
+}
+
+/// Check that an invite image exists and is a JPEG or PNG, returning its MIME type
+pub fn validate_invite_image(path: &str) -> Result<&'static str, String> {
+    let mut header = [0u8; 4];
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    std::io::Read::read_exact(&mut file, &mut header)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    match header {
+        [0xFF, 0xD8, 0xFF, _] => Ok("image/jpeg"),
+        [0x89, b'P', b'N', b'G'] => Ok("image/png"),
+        _ => Err(format!(
+            "{} is not a supported image (expected JPEG or PNG)",
+            path
+        )),
+    }
+}
+
+/// Upload an invite image and build an image message captioned with the invite text
+async fn image_invite(
+    client: &impl GroupOps,
+    path: &str,
+    caption: &str,
+) -> Result<wa::Message, String> {
+    let mimetype = validate_invite_image(path)?;
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+    let image = client.upload_image(data).await.map_err(|e| e.to_string())?;
+
+    Ok(wa::Message {
+        image_message: Some(Box::new(wa::message::ImageMessage {
+            mimetype: Some(mimetype.to_string()),
+            caption: Some(caption.to_string()),
+            ..image
+        })),
+        ..Default::default()
+    })
+}
+
+/// Outcome of sending invite messages
+#[derive(Debug, Default, Clone)]
+pub struct InviteSendStats {
+    /// Members the invite message was delivered to
+    pub sent: Vec<Jid>,
+    /// Members the invite message couldn't be sent to, with the error
+    pub failed: Vec<(Jid, String)>,
+    /// Members left out because they already received an invite in an earlier run
+    pub skipped: Vec<Jid>,
+}
+
+/// Send the group invite message to members (e.g. those who couldn't be added)
+///
+/// Members recorded in invites_sent.json are skipped.
+pub async fn send_invite_messages(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    failed_jids: &[Jid],
+    config: &RunConfig,
+) -> InviteSendStats {
+    let mut invite_stats = InviteSendStats::default();
+
+    if failed_jids.is_empty() {
+        return invite_stats;
+    }
+
+    // Load list of phones that already received invites
+    let mut invites_sent = load_invites_sent();
+
+    // Filter out JIDs that already received invite messages
+    let mut pending_jids = Vec::new();
+
+    for jid in failed_jids {
+        let phone = normalize_phone(&jid_to_phone(jid));
+        if invites_sent.contains(&phone) {
+            progress!("⊘ Skipped invite to {} (already sent)", jid);
+            invite_stats.skipped.push(jid.clone());
+        } else {
+            pending_jids.push(jid.clone());
+        }
+    }
+
+    if pending_jids.is_empty() {
+        if !invite_stats.skipped.is_empty() {
+            say!(
+                "\n✓ All {} member(s) already received invite messages",
+                invite_stats.skipped.len()
+            );
+        }
+        return invite_stats;
+    }
+
+    say!("\n=== Sending Invite Messages ===");
+    if !invite_stats.skipped.is_empty() {
+        say!(
+            "Sending invite messages to {} new members ({} already sent)\n",
+            pending_jids.len(),
+            invite_stats.skipped.len()
+        );
+    } else {
+        say!(
+            "Sending invite messages to {} member(s)\n",
+            pending_jids.len()
+        );
+    }
+
+    // Try to get the group invite link
+    let (invite_link, invite_code) = match cached_invite_link(client, group_jid).await {
+        Ok(link) => (link.url(), Some(link.code)),
+        Err(e) => {
+            eprintln!("⚠️  Failed to get group invite link: {}", e);
+            ("(ask admin for invite link)".to_string(), None)
+        }
+    };
+
+    // Load message template and interpolate the invite link
+    let (_, template) = load_invite_message_template(&config.message_file, &config.invite_lang);
+    let invite_message = template.replace("{link}", &invite_link);
+
+    let text_message = wa::Message {
+        conversation: Some(invite_message.clone()),
+        ..Default::default()
+    };
+
+    // The invite card needs the invite code; without it, fall back to text
+    let invite = match (config.invite_format, invite_code) {
+        (InviteFormat::Card, Some(invite_code)) => {
+            let group_name = client
+                .query_group_metadata(group_jid)
+                .await
+                .map(|metadata| metadata.subject)
+                .ok();
+            wa::Message {
+                group_invite_message: Some(Box::new(wa::message::GroupInviteMessage {
+                    group_jid: Some(group_jid.to_string()),
+                    invite_code: Some(invite_code),
+                    group_name,
+                    caption: Some(invite_message.clone()),
+                    ..Default::default()
+                })),
+                ..Default::default()
+            }
+        }
+        (InviteFormat::Card, None) => {
+            eprintln!("⚠️  No invite code available, sending text invites instead of cards");
+            text_message
+        }
+        (InviteFormat::Text, _) => match &config.invite_image {
+            // Upload the image once and reuse it for every recipient
+            Some(image_path) => match image_invite(client, image_path, &invite_message).await {
+                Ok(message) => message,
+                Err(e) => {
+                    eprintln!(
+                        "⚠️  Failed to upload {}, sending text invites instead: {}",
+                        image_path, e
+                    );
+                    text_message
+                }
+            },
+            None => text_message,
+        },
+    };
+
+    for jid in &pending_jids {
+        let message = invite.clone();
+
+        match send_message_with_retry(client, jid, message, config.max_retries).await {
+            Ok(()) => {
+                progress!("📧 Sent invite message to {}", jid);
+
+                // Track that invite was sent, only once the send is confirmed
+                let phone = normalize_phone(&jid_to_phone(jid));
+                if !invites_sent.contains(&phone) {
+                    invites_sent.push(phone);
+                }
+
+                // Save after every send so a crash can't cause duplicate invites
+                if let Err(e) = save_invites_sent(&invites_sent) {
+                    eprintln!("⚠️  Failed to save invites_sent.json: {}", e);
+                }
+
+                invite_stats.sent.push(jid.clone());
+            }
+            Err(e) => {
+                eprintln!("⚠️  Failed to send message to {}: {}", jid, e);
+                invite_stats.failed.push((jid.clone(), e));
+            }
+        }
+
+        tokio::time::sleep(tokio::time::Duration::from_millis(config.invite_delay_ms)).await;
+    }
+
+    invite_stats
+}
+
+/// Send a message, retrying transient failures with backoff
+///
+/// Rate-limit errors (the server's `rate-overlimit` condition) wait 30 seconds like
+/// member additions; other errors back off exponentially starting at 5 seconds.
+async fn send_message_with_retry(
+    client: &impl GroupOps,
+    jid: &Jid,
+    message: wa::Message,
+    max_retries: usize,
+) -> Result<(), String> {
+    let mut retry_count = 0;
+
+    loop {
+        match client.send_message(jid, message.clone()).await {
+            Ok(_) => return Ok(()),
+            Err(e) if retry_count < max_retries => {
+                let error_msg = e.to_string();
+                let wait_secs = if error_msg.contains("rate-overlimit") {
+                    30
+                } else {
+                    5 * 2u64.pow(retry_count as u32)
+                };
+
+                retry_count += 1;
+                progress!(
+                    "⚠️  Failed to send message to {} ({}), retry {}/{} in {} seconds...",
+                    jid,
+                    error_msg,
+                    retry_count,
+                    max_retries,
+                    wait_secs
+                );
+                tokio::time::sleep(tokio::time::Duration::from_secs(wait_secs)).await;
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
+
+/// Build the JSON-lines record describing the outcome for one member
+fn member_record(result: &AddMemberResult) -> serde_json::Value {
+    serde_json::json!({
+        "jid": result.jid.to_string(),
+        "phone": jid_to_phone(&result.jid),
+        "name": result.name,
+        "status": result.status(),
+        "error_code": result.error_code,
+        "error_text": result.error_text,
+        "ts": now_timestamp(),
+    })
+}
+
+/// Average seconds between adds so that `member_count` adds fill `window`
+pub fn spread_delay(window: Duration, member_count: usize) -> u64 {
+    let gaps = member_count.saturating_sub(1).max(1) as u64;
+    (window.as_secs() / gaps).max(1)
+}
+
+/// Rate-limit risk of a batch, as graded by `estimate_run`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl std::fmt::Display for RiskLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RiskLevel::Low => "low",
+            RiskLevel::Medium => "medium",
+            RiskLevel::High => "high",
+        })
+    }
+}
+
+/// Heuristic estimate of a batch, without any network action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunEstimate {
+    /// Approximate time the adds take at the configured delay
+    pub duration: Duration,
+    pub risk: RiskLevel,
+    /// What would lower the risk, one suggestion per raised threshold
+    pub advice: Vec<String>,
+}
+
+/// Estimate how long `adds` take at `delay_secs` each and how likely they hit 429s
+///
+/// Each threshold crossed raises the risk one level: a delay under
+/// `safe_delay_secs` (two levels under a third of it), more adds than
+/// `large_batch`, and a previous run finished less than `min_hours_between_runs` ago.
+pub fn estimate_run(
+    adds: usize,
+    delay_secs: u64,
+    since_last_run: Option<Duration>,
+    thresholds: &RiskThresholds,
+) -> RunEstimate {
+    let mut score = 0;
+    let mut advice = Vec::new();
+
+    if delay_secs < thresholds.safe_delay_secs {
+        score += if delay_secs * 3 < thresholds.safe_delay_secs {
+            2
+        } else {
+            1
+        };
+        advice.push(format!("consider ≥{}s delay", thresholds.safe_delay_secs));
+    }
+    if adds > thresholds.large_batch {
+        score += 1;
+        advice.push(format!(
+            "consider batches of ≤{} or --spread-over",
+            thresholds.large_batch
+        ));
+    }
+    if let Some(since_last_run) = since_last_run
+        && since_last_run.as_secs() < thresholds.min_hours_between_runs * 3600
+    {
+        score += 1;
+        advice.push(format!(
+            "the last run finished {}m ago, consider waiting {}h between runs",
+            since_last_run.as_secs() / 60,
+            thresholds.min_hours_between_runs
+        ));
+    }
+
+    RunEstimate {
+        duration: Duration::from_secs(adds as u64 * delay_secs),
+        risk: match score {
+            0 => RiskLevel::Low,
+            1 => RiskLevel::Medium,
+            _ => RiskLevel::High,
+        },
+        advice,
+    }
+}
+
+/// Scale a member's delay by the slowdown accumulated from global rate limits
+///
+/// The scaled delay is capped at `MAX_ADAPTIVE_DELAY_SECS`, but never below the
+/// configured delay.
+fn slowed_delay(delay_seconds: u64, slowdown: u64) -> u64 {
+    if slowdown == 1 {
+        delay_seconds
+    } else {
+        let scaled = delay_seconds
+            .saturating_mul(slowdown)
+            .clamp(1, MAX_ADAPTIVE_DELAY_SECS);
+        delay_seconds.max(scaled)
+    }
+}
+
+/// The delay between two members, randomized by ±25% when a seeded RNG is given
+fn jittered_delay(delay_seconds: u64, rng: Option<&mut StdRng>) -> Duration {
+    let base = Duration::from_secs(delay_seconds);
+    match rng {
+        Some(rng) => base.mul_f64(rng.random_range(0.75..=1.25)),
+        None => base,
+    }
+}
+
+/// Process adding multiple members one by one with delays
+pub async fn add_members_batch(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    member_jids: &[Jid],
+    config: &RunConfig,
+) -> AddMemberStats {
+    add_members_chunked(client, group_jid, member_jids, 1, config).await
+}
+
+/// Process adding multiple members, sending up to `chunk_size` of them per add request
+///
+/// With a `chunk_size` of 1 this is the one-by-one `add_members_batch`. Larger
+/// chunks need fewer requests and delays; members whose chunked answer isn't final
+/// (rate limits, unknown codes) are retried one by one.
+pub async fn add_members_chunked(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    member_jids: &[Jid],
+    chunk_size: usize,
+    config: &RunConfig,
+) -> AddMemberStats {
+    let chunk_size = chunk_size.clamp(1, groups::MAX_PARTICIPANTS_PER_IQ);
+    let mut stats = AddMemberStats::default();
+    // Doubled after every global rate limit, applied on top of each member's delay
+    let mut slowdown: u64 = 1;
+
+    let mut report = RunReport::new(group_jid, config.shuffle_seed);
+    report.label = config.label.clone();
+
+    // A seeded run shuffles the member order and jitters delays reproducibly
+    let mut rng = config.shuffle_seed.map(StdRng::seed_from_u64);
+    let mut member_jids = member_jids.to_vec();
+    if let Some(rng) = rng.as_mut() {
+        member_jids.shuffle(rng);
+    }
+
+    if let Some(reference_jid) = &config.only_from {
+        let reference = match client.query_group_metadata(reference_jid).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!(
+                    "✗ Failed to fetch participants of reference group {}: {}",
+                    reference_jid, e
+                );
+                eprintln!("   Aborting the run rather than adding unverified members.");
+                stats.report = Some(report);
+                return stats;
+            }
+        };
+
+        let allowed: Vec<String> = reference
+            .participants
+            .iter()
+            .map(|participant| normalize_phone(&jid_to_phone(&participant.jid)))
+            .collect();
+
+        let before = member_jids.len();
+        member_jids.retain(|jid| allowed.contains(&normalize_phone(&jid_to_phone(jid))));
+        stats.total_filtered = before - member_jids.len();
+
+        say!(
+            "Only adding members of \"{}\": {} of {} kept, {} filtered out\n",
+            reference.subject,
+            member_jids.len(),
+            before,
+            stats.total_filtered
+        );
+    }
+
+    // Spreading over a window replaces the fixed delay with the average pace, jittered
+    let spread_config;
+    let config = match config.spread_over {
+        Some(window) => {
+            let pace = spread_delay(window, member_jids.len());
+            say!(
+                "Spreading {} members over {} min: ~{}s between each",
+                member_jids.len(),
+                window.as_secs() / 60,
+                pace
+            );
+            rng.get_or_insert_with(|| StdRng::seed_from_u64(rand::random()));
+            spread_config = RunConfig {
+                delay_seconds: pace,
+                ..config.clone()
+            };
+            &spread_config
+        }
+        None => config,
+    };
+
+    say!(
+        "Adding {} members one by one ({}s delay between each)...\n",
+        member_jids.len(),
+        config.delay_seconds
+    );
+    if !config.country_profiles.is_empty() {
+        say!(
+            "Using country profiles for prefixes: {}\n",
+            config
+                .country_profiles
+                .keys()
+                .map(|prefix| format!("+{}", prefix))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let blocklist = load_blocklist();
+    if !blocklist.is_empty() {
+        say!("Loaded {} blocklisted phone(s)\n", blocklist.len());
+    }
+    if let Some(consented) = &config.consented_phones {
+        say!(
+            "Only adding the {} phone(s) with consent on record\n",
+            consented.len()
+        );
+    }
+    let has_consent = |phone: &String| {
+        config
+            .consented_phones
+            .as_ref()
+            .is_none_or(|consented| consented.contains(phone))
+    };
+
+    let previously_added = if config.skip_previously_added {
+        let phones = load_previously_added(group_jid);
+        say!(
+            "Skipping {} member(s) recorded as previously added\n",
+            phones.len()
+        );
+        phones
+    } else {
+        Vec::new()
+    };
+
+    // Current participants are skipped without an add attempt (and without a delay)
+    // The group creator can't be added (or removed) like other members
+    let (current_members, creator): (Vec<String>, Option<String>) =
+        match client.query_group_metadata(group_jid).await {
+            Ok(metadata) => (
+                metadata
+                    .participants
+                    .iter()
+                    .map(|participant| normalize_phone(&jid_to_phone(&participant.jid)))
+                    .collect(),
+                metadata
+                    .owner
+                    .as_ref()
+                    .map(|owner| normalize_phone(&jid_to_phone(owner))),
+            ),
+            Err(e) => {
+                log::debug!("Could not pre-fetch participants of {}: {}", group_jid, e);
+                (Vec::new(), None)
+            }
+        };
+
+    let should_skip = |phone: &String| {
+        blocklist.contains(phone)
+            || !has_consent(phone)
+            || previously_added.contains(phone)
+            || current_members.contains(phone)
+            || creator.as_ref() == Some(phone)
+    };
+    // Members already sent as part of a chunk, and the final outcomes among them, by user
+    let mut chunked: HashSet<String> = HashSet::new();
+    let mut chunk_outcomes: HashMap<String, AddMemberResult> = HashMap::new();
+
+    // The delay only separates real add attempts, not instant skips
+    let mut pending_delay = false;
+    let mut first_attempt = true;
+    let mut consecutive_failures = 0;
+    let started = std::time::Instant::now();
+
+    for (index, jid) in member_jids.iter().enumerate() {
+        pause::wait_while_paused().await;
+
+        // After the breaker trips, only members a chunk already sent are still recorded
+        if stats.too_many_failures {
+            if chunk_outcomes.is_empty() {
+                break;
+            }
+            if !chunk_outcomes.contains_key(&jid.user) {
+                continue;
+            }
+        }
+
+        // Members already added as part of a chunk are still recorded
+        if let Some(max_duration) = config.max_duration
+            && started.elapsed() >= max_duration
+            && chunk_outcomes.is_empty()
+        {
+            stats.total_remaining = member_jids.len() - index;
+            say!(
+                "\n⏱️  Reached the maximum run duration ({} min), stopping",
+                max_duration.as_secs() / 60
+            );
+            break;
+        }
+
+        progress!("=== Adding member {}/{} ===", index + 1, member_jids.len());
+
+        let phone = normalize_phone(&jid_to_phone(jid));
+        if blocklist.contains(&phone) {
+            progress!("⛔ Skipped: {} (blocklisted)\n", phone);
+            emit_jsonl(&serde_json::json!({
+                "jid": jid.to_string(),
+                "phone": jid_to_phone(jid),
+                "status": "blocked",
+                "error_code": null,
+                "ts": now_timestamp(),
+            }));
+            report.record_blocked(jid);
+            stats.total_blocked += 1;
+            checkpoint_report(&report, index + 1, config);
+            continue;
+        }
+
+        if !has_consent(&phone) {
+            progress!("⛔ Refused: {} (no consent on record)\n", phone);
+            emit_jsonl(&serde_json::json!({
+                "jid": jid.to_string(),
+                "phone": jid_to_phone(jid),
+                "status": "no_consent",
+                "error_code": null,
+                "ts": now_timestamp(),
+            }));
+            report.record_no_consent(jid);
+            stats.total_no_consent += 1;
+            checkpoint_report(&report, index + 1, config);
+            continue;
+        }
+
+        if previously_added.contains(&phone) {
+            progress!("⊘ Skipped: {} (previously added)\n", jid);
+            let mut result = AddMemberResult::new(jid.clone());
+            result.skipped = true;
+            result.error_text = Some("Previously added by an earlier run".to_string());
+            emit_jsonl(&member_record(&result));
+            report.record(&result);
+            stats.total_skipped += 1;
+            checkpoint_report(&report, index + 1, config);
+            continue;
+        }
+
+        if creator.as_ref() == Some(&phone) {
+            progress!("⊘ Skipped: {} (group creator)\n", jid);
+            let mut result = AddMemberResult::new(jid.clone());
+            result.skipped = true;
+            result.error_text = Some("Group creator".to_string());
+            emit_jsonl(&member_record(&result));
+            report.record(&result);
+            stats.total_skipped += 1;
+            stats.skipped_creator = Some(jid.clone());
+            checkpoint_report(&report, index + 1, config);
+            continue;
+        }
+
+        if current_members.contains(&phone) {
+            progress!("⊘ Skipped: {} (already in group)\n", jid);
+            let mut result = AddMemberResult::new(jid.clone());
+            result.skipped = true;
+            result.error_text = Some(describe_add_error(409).to_string());
+            emit_jsonl(&member_record(&result));
+            report.record(&result);
+            stats.total_skipped += 1;
+            checkpoint_report(&report, index + 1, config);
+            continue;
+        }
+
+        let result = match chunk_outcomes.remove(&jid.user) {
+            Some(result) => result,
+            None => {
+                if pending_delay {
+                    let delay_seconds = slowed_delay(config.delay_for(&phone), slowdown);
+                    let delay = jittered_delay(delay_seconds, rng.as_mut());
+                    progress!(
+                        "Waiting {:.1} seconds before next member...\n",
+                        delay.as_secs_f64()
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                pending_delay = true;
+
+                // A member whose chunked answer wasn't final is retried on its own
+                if chunk_size > 1 && !chunked.contains(&jid.user) {
+                    let chunk: Vec<Jid> = std::iter::once(jid.clone())
+                        .chain(
+                            member_jids[index + 1..]
+                                .iter()
+                                .filter(|next| !should_skip(&normalize_phone(&jid_to_phone(next))))
+                                .take(chunk_size - 1)
+                                .cloned(),
+                        )
+                        .collect();
+                    chunked.extend(chunk.iter().map(|member| member.user.clone()));
+                    chunk_outcomes
+                        .extend(add_chunk(client, group_jid, &chunk, config.member_ttl).await);
+                }
+
+                match chunk_outcomes.remove(&jid.user) {
+                    Some(result) => result,
+                    None => {
+                        let policy = config.retry_policy_for(&phone);
+                        add_member_with_retry(client, group_jid, jid, &policy).await
+                    }
+                }
+            }
+        };
+        stats.rate_limit_retries += result.rate_limit_retries;
+        stats.rate_limit_wait += result.rate_limit_wait;
+        emit_jsonl(&member_record(&result));
+        report.record(&result);
+        if let Some(audit_log) = &config.audit_log
+            && let Err(e) = append_audit_event(audit_log, group_jid, &result)
+        {
+            eprintln!("⚠️  Failed to write the audit log: {}", e);
+        }
+        checkpoint_report(&report, index + 1, config);
+
+        if result.account_restricted {
+            eprintln!(
+                "\n⛔ Account restricted: WhatsApp is blocking this account from adding members."
+            );
+            eprintln!("   Aborting the run. Wait before trying again to avoid a permanent ban.");
+            stats.total_failed += 1;
+            stats.account_restricted = true;
+            break;
+        }
+
+        // A 403 on the very first add means every other add will be refused too,
+        // unless we are an admin and this member's privacy settings refused it
+        if std::mem::replace(&mut first_attempt, false)
+            && result.error_code == Some(403)
+            && config.abort_on_not_admin
+            && is_group_admin(client, group_jid).await == Some(false)
+        {
+            eprintln!("\n⛔ You're not an admin of this group, so no member can be added.");
+            eprintln!(
+                "   Aborting the run. Set abort_on_not_admin = false to invite members instead."
+            );
+            stats.total_failed += 1;
+            stats.not_admin = true;
+            break;
+        }
+
+        if result.global_rate_limited {
+            slowdown = (slowdown * 2).min(MAX_ADAPTIVE_DELAY_SECS);
+            say!(
+                "🐢 Slowing down after a global rate limit: {}s between members",
+                slowed_delay(config.delay_seconds, slowdown)
+            );
+        }
+
+        if result.success {
+            consecutive_failures = 0;
+        } else if !result.skipped {
+            consecutive_failures += 1;
+        }
+
+        if result.success {
+            stats.total_success += 1;
+            stats.added_jids.push(jid.clone());
+
+            if let Err(e) = save_added_members(group_jid, std::slice::from_ref(jid), &config.label)
+            {
+                eprintln!("⚠️  Failed to record addition in added_members.json: {}", e);
+            }
+        } else if result.skipped {
+            stats.total_skipped += 1;
+        } else {
+            stats.total_failed += 1;
+
+            if result.should_track_invalid {
+                let phone = jid_to_phone(jid);
+                stats.invalid_phones.push(phone);
+            }
+
+            if result.should_send_invite {
+                stats.failed_for_invite.push(jid.clone());
+            }
+        }
+
+        if let Some(max_failures) = config.max_consecutive_failures
+            && consecutive_failures >= max_failures
+            && !stats.too_many_failures
+        {
+            eprintln!(
+                "\n⛔ {} adds failed in a row, something is wrong with this run.",
+                consecutive_failures
+            );
+            eprintln!("   Aborting the run. Check the errors above before trying again.");
+            stats.too_many_failures = true;
+        }
+    }
+
+    if config.verify_adds && !stats.added_jids.is_empty() {
+        verify_added_members(client, group_jid, &mut stats).await;
+    }
+
+    if config.assign_role == Some(Role::Admin) && !stats.added_jids.is_empty() {
+        promote_added_members(client, group_jid, &mut stats).await;
+    }
+
+    if let Some(welcome_message) = &config.welcome_message
+        && !stats.added_jids.is_empty()
+    {
+        send_welcome_message(
+            client,
+            group_jid,
+            welcome_message,
+            stats.added_jids.len(),
+            config,
+        )
+        .await;
+    }
+
+    stats.report = Some(report);
+    stats
+}
+
+/// Re-query the group and record the members reported added that aren't in it
+///
+/// WhatsApp occasionally acknowledges an add that never takes effect, which the
+/// per-member result can't reveal.
+async fn verify_added_members(client: &impl GroupOps, group_jid: &Jid, stats: &mut AddMemberStats) {
+    say!(
+        "\n🔎 Verifying {} added member(s) are in the group...",
+        stats.added_jids.len()
+    );
+
+    let metadata = match client.query_group_metadata(group_jid).await {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            eprintln!("⚠️  Could not verify the added members: {}", e);
+            return;
+        }
+    };
+    let participants: HashSet<String> = metadata
+        .participants
+        .iter()
+        .map(|participant| normalize_phone(&jid_to_phone(&participant.jid)))
+        .collect();
+
+    stats.unconfirmed_adds = stats
+        .added_jids
+        .iter()
+        .filter(|jid| !participants.contains(&normalize_phone(&jid_to_phone(jid))))
+        .cloned()
+        .collect();
+    for jid in &stats.unconfirmed_adds {
+        eprintln!("⚠️  {} was reported added but isn't in the group", jid);
+    }
+}
+
+/// Promote every member added by the batch to admin, recording the outcome in `stats`
+async fn promote_added_members(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    stats: &mut AddMemberStats,
+) {
+    say!(
+        "\n👑 Promoting {} added member(s) to admin...",
+        stats.added_jids.len()
+    );
+
+    match client
+        .promote_group_participants(group_jid, &stats.added_jids)
+        .await
+    {
+        Ok(results) => {
+            for (jid, success, error_code) in results {
+                if success {
+                    stats.total_promoted += 1;
+                } else {
+                    eprintln!("✗ Failed to promote {} (error code: {:?})", jid, error_code);
+                    stats.promote_failed.push(jid);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("✗ Failed to promote added members: {}", e);
+            stats.promote_failed = stats.added_jids.clone();
+        }
+    }
+}
+
+/// Post the welcome message in the group once, with `{count}` set to the members added
+async fn send_welcome_message(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    template: &str,
+    added_count: usize,
+    config: &RunConfig,
+) {
+    let message = wa::Message {
+        conversation: Some(template.trim().replace("{count}", &added_count.to_string())),
+        ..Default::default()
+    };
+
+    match send_message_with_retry(client, group_jid, message, config.max_retries).await {
+        Ok(()) => say!("\n👋 Posted welcome message in the group"),
+        Err(e) => eprintln!("⚠️  Failed to post welcome message: {}", e),
+    }
+}
+
+/// Demote every admin of the group that isn't in `keep`
+///
+/// Returns `(participant_jid, success, error_code)` for each admin demoted; an empty
+/// list when only kept accounts are admins.
+pub async fn demote_admins_except(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    keep: &[Jid],
+) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+    let mut metadata = client.query_group_metadata(group_jid).await?;
+    if metadata.participants.iter().any(|participant| {
+        (participant.is_admin || participant.is_super_admin) && participant.is_unmapped_lid()
+    }) {
+        let mut mapping = Vec::new();
+        for kept in keep {
+            if let Ok(Some(lid)) = client.resolve_lid(kept).await {
+                mapping.push((kept.clone(), lid));
+            }
+        }
+        metadata.map_lids(&mapping);
+    }
+    let to_demote = groups::admins_to_demote(&metadata, keep)?;
+
+    if to_demote.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    say!(
+        "Demoting {} of {} admin(s) in \"{}\"...",
+        to_demote.len(),
+        metadata.admins().len(),
+        metadata.subject
+    );
+    client
+        .demote_group_participants(group_jid, &to_demote)
+        .await
+}
+
+/// Whether the connected account is an admin of the group, `None` when unknown
+async fn is_group_admin(client: &impl GroupOps, group_jid: &Jid) -> Option<bool> {
+    let own_jids = client.own_jids().await;
+    if own_jids.is_empty() {
+        return None;
+    }
+
+    match client.query_group_metadata(group_jid).await {
+        Ok(metadata) => Some(own_jids.iter().any(|own_jid| metadata.i_am_admin(own_jid))),
+        Err(e) => {
+            log::debug!("Could not check admin rights in {}: {}", group_jid, e);
+            None
+        }
+    }
+}
+
+/// Write the in-progress run report every `config.checkpoint_every` members
+fn checkpoint_report(report: &RunReport, processed: usize, config: &RunConfig) {
+    if config.checkpoint_every == 0 || !processed.is_multiple_of(config.checkpoint_every) {
+        return;
+    }
+
+    match report.save(&config.report_file) {
+        Ok(()) => say!(
+            "💾 Checkpointed {} after {} members",
+            config.report_file,
+            processed
+        ),
+        Err(e) => eprintln!("⚠️  Failed to checkpoint run report: {}", e),
+    }
+}
+
+pub async fn finalize_member_addition(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    mut stats: AddMemberStats,
+    config: &RunConfig,
+) {
+    say!("\n=== Final Summary ===");
+    say!(
+        "{}",
+        output::green(&format!("✓ Successfully added: {}", stats.total_success))
+    );
+    say!("⊘ Skipped: {}", stats.total_skipped);
+    say!(
+        "{}",
+        output::red(&format!("✗ Failed: {}", stats.total_failed))
+    );
+    if stats.total_blocked > 0 {
+        say!("⛔ Blocked: {}", stats.total_blocked);
+    }
+    if stats.total_no_consent > 0 {
+        say!("⛔ Refused (no consent): {}", stats.total_no_consent);
+    }
+    if stats.total_filtered > 0 {
+        say!("⊘ Not in reference group: {}", stats.total_filtered);
+    }
+    if stats.account_restricted {
+        say!("⛔ Run aborted early: account restricted by WhatsApp");
+    }
+    if stats.not_admin {
+        say!("⛔ Run aborted early: you're not an admin of this group");
+    }
+    if stats.too_many_failures {
+        say!("⛔ Run aborted early: too many consecutive failures");
+    }
+    if let Some(creator) = &stats.skipped_creator {
+        say!("⊘ Group creator left as is: {}", creator);
+    }
+    if stats.total_remaining > 0 {
+        say!(
+            "⏱️  Stopped after reaching max duration, {} remaining",
+            stats.total_remaining
+        );
+        say!("   Run again with --skip-previously-added to continue where this run stopped");
+    }
+    if !stats.unconfirmed_adds.is_empty() {
+        say!(
+            "{}",
+            output::red(&format!(
+                "⚠️  Reported added but not found: {}",
+                stats.unconfirmed_adds.len()
+            ))
+        );
+    }
+    if stats.total_promoted > 0 || !stats.promote_failed.is_empty() {
+        say!(
+            "👑 Promoted to admin: {} (failed: {})",
+            stats.total_promoted,
+            stats.promote_failed.len()
+        );
+    }
+    if stats.rate_limit_retries > 0 {
+        say!(
+            "🐢 Rate-limited {} times, {}s spent waiting",
+            stats.rate_limit_retries,
+            stats.rate_limit_wait.as_secs()
+        );
+    }
+    let total_processed = stats.total_success
+        + stats.total_skipped
+        + stats.total_failed
+        + stats.total_blocked
+        + stats.total_no_consent;
+    say!("Total processed: {}", total_processed);
+    emit_jsonl(&serde_json::json!({
+        "summary": true,
+        "group": group_jid.to_string(),
+        "success": stats.total_success,
+        "skipped": stats.total_skipped,
+        "failed": stats.total_failed,
+        "blocked": stats.total_blocked,
+        "no_consent": stats.total_no_consent,
+        "total": total_processed,
+        "remaining": stats.total_remaining,
+        "unconfirmed": stats.unconfirmed_adds.len(),
+        "rate_limit_retries": stats.rate_limit_retries,
+        "rate_limit_wait_secs": stats.rate_limit_wait.as_secs(),
+        "ts": now_timestamp(),
+    }));
+
+    if !stats.failed_for_invite.is_empty() {
+        if config.send_invite {
+            let invite_stats =
+                send_invite_messages(client, group_jid, &stats.failed_for_invite, config).await;
+
+            if !invite_stats.failed.is_empty() {
+                say!(
+                    "\n✗ Couldn't add or invite {} member(s):",
+                    invite_stats.failed.len()
+                );
+                for (jid, error) in &invite_stats.failed {
+                    say!("  {} ({})", jid, error);
+                }
+            }
+            if !invite_stats.sent.is_empty() || !invite_stats.failed.is_empty() {
+                match save_failed_invites(&invite_stats) {
+                    Ok(total) => say!(
+                        "📝 {} member(s) awaiting an invite in failed_invites.json",
+                        total
+                    ),
+                    Err(e) => eprintln!("⚠️  Failed to save failed_invites.json: {}", e),
+                }
+            }
+        } else {
+            say!(
+                "\nInvite fallback disabled: {} member(s) were not sent an invite link",
+                stats.failed_for_invite.len()
+            );
+        }
+    }
+
+    if !stats.invalid_phones.is_empty() {
+        match save_invalid_phones(&stats.invalid_phones) {
+            Ok(total) => say!(
                 "\n📝 Saved {} invalid phone numbers to invalid_phones.json",
                 total
             ),
             Err(e) => eprintln!("⚠️  Failed to save invalid_phones.json: {}", e),
         }
     }
+
+    if let Some(report) = stats.report.as_mut() {
+        report.finish();
+
+        match update_retry_queue(group_jid, report) {
+            Ok(0) => {}
+            Ok(queued) => say!(
+                "\n🔁 {} member(s) queued in retry_queue.json (pass --resume-retries next time)",
+                queued
+            ),
+            Err(e) => eprintln!("⚠️  Failed to update retry_queue.json: {}", e),
+        }
+
+        match report.save(&config.report_file) {
+            Ok(()) => say!("📊 Saved run report to {}", config.report_file),
+            Err(e) => eprintln!("⚠️  Failed to save run report: {}", e),
+        }
+
+        if let Some(csv_path) = &config.report_csv {
+            match report.save_csv(csv_path) {
+                Ok(()) => say!("📊 Saved CSV report to {}", csv_path),
+                Err(e) => eprintln!("⚠️  Failed to save CSV report: {}", e),
+            }
+        }
+
+        if let Some(metrics_path) = &config.metrics_file {
+            let metrics = RunMetrics {
+                group: group_jid.to_string(),
+                label: config.label.clone(),
+                adds_success: stats.total_success,
+                adds_failed: stats.total_failed,
+                rate_limited: stats.rate_limit_retries,
+                duration: report.duration().unwrap_or_default(),
+            };
+            match metrics.save(metrics_path) {
+                Ok(()) => say!("📈 Saved metrics to {}", metrics_path),
+                Err(e) => eprintln!("⚠️  Failed to save metrics: {}", e),
+            }
+        }
+
+        if let Some(url) = config.webhook.clone() {
+            let report = report.clone();
+            match tokio::task::spawn_blocking(move || report.send_webhook(&url)).await {
+                Ok(Ok(())) => say!("🔔 Notified webhook"),
+                Ok(Err(e)) => eprintln!("⚠️  Webhook notification failed: {}", e),
+                Err(e) => eprintln!("⚠️  Webhook notification failed: {}", e),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RemoveMemberStats {
+    pub total_removed: usize,
+    pub total_failed: usize,
+    /// The group owner, when the list contained them (never removable, counted as failed)
+    pub owner_refused: Option<Jid>,
+}
+
+/// Participants of a group whose number is no longer registered on WhatsApp
+///
+/// Only phone-number participants are checked; LID participants are accounts by
+/// definition. Numbers the server doesn't answer for are kept.
+pub async fn find_ghost_members(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+) -> anyhow::Result<Vec<Jid>> {
+    let metadata = client.query_group_metadata(group_jid).await?;
+    let phone_jids: Vec<Jid> = metadata
+        .participants
+        .into_iter()
+        .map(|participant| participant.jid)
+        .filter(|jid| jid.server == "s.whatsapp.net")
+        .collect();
+
+    if phone_jids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(client
+        .check_numbers_on_whatsapp(&phone_jids)
+        .await?
+        .into_iter()
+        .filter(|(_, registered)| !registered)
+        .map(|(jid, _)| jid)
+        .collect())
+}
+
+/// Process removing multiple members one by one with delays
+pub async fn remove_members_batch(
+    client: &impl GroupOps,
+    group_jid: &Jid,
+    member_jids: &[Jid],
+    config: &RunConfig,
+) -> RemoveMemberStats {
+    let mut stats = RemoveMemberStats::default();
+
+    let owner = match client.query_group_metadata(group_jid).await {
+        Ok(metadata) => metadata.owner,
+        Err(e) => {
+            log::debug!("Could not fetch the owner of {}: {}", group_jid, e);
+            None
+        }
+    };
+
+    for (index, jid) in member_jids.iter().enumerate() {
+        progress!(
+            "=== Removing member {}/{} ===",
+            index + 1,
+            member_jids.len()
+        );
+
+        if owner.as_ref().is_some_and(|owner| owner.user == jid.user) {
+            eprintln!("✗ Cannot remove {}: they are the group owner", jid);
+            stats.total_failed += 1;
+            stats.owner_refused = Some(jid.clone());
+            continue;
+        }
+
+        match client
+            .remove_group_participants(group_jid, std::slice::from_ref(jid))
+            .await
+        {
+            Ok(results) => {
+                for (jid, success, error_code) in results {
+                    if success {
+                        progress!("✓ Successfully removed: {}", jid);
+                        stats.total_removed += 1;
+                        continue;
+                    }
+
+                    progress!("✗ Failed to remove: {} (error code: {:?})", jid, error_code);
+                    stats.total_failed += 1;
+
+                    match error_code {
+                        Some(400) => progress!("   → Bad request (invalid phone number)"),
+                        Some(403) => progress!(
+                            "   → Not authorized (you may not be an admin, or the user is the group owner)"
+                        ),
+                        Some(404) => progress!("   → User is not in the group"),
+                        Some(429) => progress!("   → Rate limit exceeded"),
+                        Some(_) => progress!("   → Unknown error code"),
+                        None => {}
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("✗ Failed to remove {}: {}", jid, e);
+                stats.total_failed += 1;
+            }
+        }
+
+        if index < member_jids.len() - 1 {
+            say!(
+                "Waiting {} seconds before next member...\n",
+                config.delay_seconds
+            );
+            tokio::time::sleep(tokio::time::Duration::from_secs(config.delay_seconds)).await;
+        }
+    }
+
+    say!("\n=== Final Summary ===");
+    say!("✓ Successfully removed: {}", stats.total_removed);
+    say!("✗ Failed: {}", stats.total_failed);
+    if let Some(owner) = &stats.owner_refused {
+        say!("⛔ Not removed, group owner: {}", owner);
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::groups::{GroupMetadata, GroupParticipant};
+
+    const INVITE_CODE: &str = "AbCdEfGhIjKlMnOpQrStUv";
+
+    /// Scripted stand-in for `Client`: add outcomes by phone, recorded calls
+    #[derive(Default)]
+    struct MockGroupOps {
+        /// Current participants of the group
+        participants: Vec<Jid>,
+        /// Creator of the group
+        owner: Option<Jid>,
+        /// Per-participant add error code by phone; absent phones are added
+        add_errors: HashMap<String, u64>,
+        /// Users whose adds are reported successful but never show up in the group
+        dropped_adds: HashSet<String>,
+        add_calls: Mutex<Vec<Jid>>,
+        sent: Mutex<Vec<(Jid, wa::Message)>>,
+        /// Users whose messages fail to send
+        send_errors: HashSet<String>,
+        /// Users no longer registered on WhatsApp
+        unregistered: HashSet<String>,
+        /// Number of invite link queries received
+        invite_link_queries: std::sync::atomic::AtomicUsize,
+        /// The connected account
+        own_jid: Option<Jid>,
+        /// Users whose add request fails outright, with this error
+        add_failures: HashMap<String, String>,
+        /// Whether the connection is reported as down
+        disconnected: bool,
+        /// Groups whose metadata query fails
+        metadata_errors: HashSet<String>,
+        /// Users listed as admins of the group
+        admins: HashSet<String>,
+    }
+
+    /// Serializes the tests that run adds or invites, which write invites_sent.json and
+    /// added_members.json in the shared output directory
+    static OUTPUT_FILES_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    impl GroupOps for MockGroupOps {
+        async fn add_group_participants(
+            &self,
+            _group_jid: &Jid,
+            participant_jids: &[Jid],
+        ) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+            self.add_calls
+                .lock()
+                .unwrap()
+                .extend_from_slice(participant_jids);
+            if let Some(error) = participant_jids
+                .iter()
+                .find_map(|jid| self.add_failures.get(&jid.user))
+            {
+                anyhow::bail!("{}", error);
+            }
+
+            Ok(participant_jids
+                .iter()
+                .map(|jid| match self.add_errors.get(&jid.user) {
+                    Some(&code) => (jid.clone(), false, Some(code)),
+                    None => (jid.clone(), true, None),
+                })
+                .collect())
+        }
+
+        async fn add_group_participants_with_ttl(
+            &self,
+            group_jid: &Jid,
+            participant_jids: &[Jid],
+            _member_ttl: Duration,
+        ) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+            self.add_group_participants(group_jid, participant_jids)
+                .await
+        }
+
+        async fn remove_group_participants(
+            &self,
+            _group_jid: &Jid,
+            participant_jids: &[Jid],
+        ) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+            Ok(participant_jids
+                .iter()
+                .map(|jid| (jid.clone(), true, None))
+                .collect())
+        }
+
+        async fn promote_group_participants(
+            &self,
+            _group_jid: &Jid,
+            participant_jids: &[Jid],
+        ) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+            Ok(participant_jids
+                .iter()
+                .map(|jid| (jid.clone(), true, None))
+                .collect())
+        }
+
+        async fn demote_group_participants(
+            &self,
+            _group_jid: &Jid,
+            participant_jids: &[Jid],
+        ) -> anyhow::Result<Vec<(Jid, bool, Option<u64>)>> {
+            Ok(participant_jids
+                .iter()
+                .map(|jid| (jid.clone(), true, None))
+                .collect())
+        }
+
+        async fn query_group_metadata(&self, group_jid: &Jid) -> anyhow::Result<GroupMetadata> {
+            if self.metadata_errors.contains(&group_jid.user) {
+                anyhow::bail!("item-not-found");
+            }
+            // Successful adds join the group, unless scripted to be dropped
+            let added: Vec<Jid> = self
+                .add_calls
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|jid| {
+                    !self.add_errors.contains_key(&jid.user)
+                        && !self.dropped_adds.contains(&jid.user)
+                })
+                .cloned()
+                .collect();
+            let participants: Vec<&Jid> = self.participants.iter().chain(&added).collect();
+
+            Ok(GroupMetadata {
+                jid: group_jid.clone(),
+                subject: "Test Group".to_string(),
+                participant_count: participants.len(),
+                is_community: false,
+                linked_subgroups: Vec::new(),
+                approval_required: false,
+                participants: participants
+                    .into_iter()
+                    .map(|jid| GroupParticipant {
+                        jid: jid.clone(),
+                        is_admin: self.admins.contains(&jid.user),
+                        is_super_admin: false,
+                        phone_number: None,
+                    })
+                    .collect(),
+                owner: self.owner.clone(),
+                ephemeral: None,
+            })
+        }
+
+        async fn is_participant(&self, _group_jid: &Jid, user: &Jid) -> anyhow::Result<bool> {
+            Ok(self
+                .participants
+                .iter()
+                .any(|participant| participant.user == user.user))
+        }
+
+        async fn get_group_invite_link(&self, _group_jid: &Jid) -> anyhow::Result<InviteLink> {
+            self.invite_link_queries
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            Ok(InviteLink::new(INVITE_CODE))
+        }
+
+        async fn resolve_push_name(&self, _jid: &Jid) -> anyhow::Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn resolve_lid(&self, _phone_jid: &Jid) -> anyhow::Result<Option<Jid>> {
+            Ok(None)
+        }
+
+        async fn own_jids(&self) -> Vec<Jid> {
+            self.own_jid.iter().cloned().collect()
+        }
+
+        async fn is_connected(&self) -> bool {
+            !self.disconnected
+        }
+
+        async fn send_message(&self, to: &Jid, message: wa::Message) -> anyhow::Result<()> {
+            if self.send_errors.contains(&to.user) {
+                anyhow::bail!("send failed");
+            }
+            self.sent.lock().unwrap().push((to.clone(), message));
+            Ok(())
+        }
+
+        async fn upload_image(&self, _data: Vec<u8>) -> anyhow::Result<wa::message::ImageMessage> {
+            anyhow::bail!("uploads are not scripted")
+        }
+
+        async fn check_numbers_on_whatsapp(
+            &self,
+            jids: &[Jid],
+        ) -> anyhow::Result<Vec<(Jid, bool)>> {
+            Ok(jids
+                .iter()
+                .map(|jid| (jid.clone(), !self.unregistered.contains(&jid.user)))
+                .collect())
+        }
+    }
+
+    fn jid(s: &str) -> Jid {
+        s.parse().unwrap()
+    }
+
+    fn group() -> Jid {
+        jid("120363000000000001@g.us")
+    }
+
+    /// A run without delays whose generated files go to a per-process temp directory
+    fn test_config() -> RunConfig {
+        let dir =
+            std::env::temp_dir().join(format!("whatsapp-invites-test-{}", std::process::id()));
+        set_output_dir(&dir.to_string_lossy()).unwrap();
+
+        RunConfig {
+            delay_seconds: 0,
+            invite_delay_ms: 0,
+            checkpoint_every: 0,
+            message_file: "missing-message.txt".to_string(),
+            report_file: output_path(crate::report::DEFAULT_REPORT_FILE),
+            ..RunConfig::default()
+        }
+    }
+
+    #[test]
+    fn jid_to_phone_strips_server() {
+        assert_eq!(
+            jid_to_phone(&jid("1234567890@s.whatsapp.net")),
+            "1234567890"
+        );
+    }
+
+    #[test]
+    fn jid_to_phone_strips_device_suffix() {
+        assert_eq!(
+            jid_to_phone(&jid("1234567890:12@s.whatsapp.net")),
+            "1234567890"
+        );
+        assert_eq!(
+            jid_to_phone(&jid("1234567890.0:12@s.whatsapp.net")),
+            "1234567890"
+        );
+    }
+
+    #[test]
+    fn jid_to_phone_handles_lids() {
+        assert_eq!(jid_to_phone(&jid("123456789012345@lid")), "123456789012345");
+        assert_eq!(
+            jid_to_phone(&jid("123456789012345:3@lid")),
+            "123456789012345"
+        );
+    }
+
+    #[test]
+    fn slowdown_never_shortens_the_configured_delay() {
+        assert_eq!(slowed_delay(10, 1), 10);
+        assert_eq!(slowed_delay(10, 4), 40);
+        assert_eq!(slowed_delay(100, 8), MAX_ADAPTIVE_DELAY_SECS);
+        assert_eq!(slowed_delay(600, 2), 600);
+        assert_eq!(slowed_delay(0, 2), 1);
+    }
+
+    #[test]
+    fn displays_lids_as_is() {
+        assert_eq!(
+            display_member(&jid("1234567890@s.whatsapp.net"), Some("Alice")),
+            "+1234567890 (Alice)"
+        );
+        assert_eq!(
+            display_member(&jid("123456789012345@lid"), None),
+            "123456789012345@lid"
+        );
+    }
+
+    #[test]
+    fn finds_group_keys_listed_twice() {
+        let data = r#"{"120363000000000001@g.us": ["111"], "120363000000000002@g.us": ["222"], "120363000000000001@g.us": ["333"]}"#;
+
+        assert_eq!(
+            duplicate_json_key(data),
+            Some("120363000000000001@g.us".to_string())
+        );
+        assert_eq!(duplicate_json_key(r#"{"a": ["1"], "b": ["2"]}"#), None);
+        assert_eq!(duplicate_json_key(r#"["111", "111"]"#), None);
+    }
+
+    #[test]
+    fn reads_first_csv_column_after_header() {
+        let csv = "phone,name\n+212 612-345678,Amina\n\n\"15550000001\",\"Doe, John\"\n";
+
+        assert_eq!(
+            parse_phones_csv(csv),
+            vec!["+212 612-345678".to_string(), "15550000001".to_string()]
+        );
+        assert_eq!(parse_phones_csv("15550000002\n"), vec!["15550000002"]);
+    }
+
+    #[test]
+    fn rotates_history_files_over_the_size_limit() {
+        let dir =
+            std::env::temp_dir().join(format!("whatsapp-invites-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir
+            .join("added_members.json")
+            .to_string_lossy()
+            .into_owned();
+        fs::write(&path, "[]").unwrap();
+        let rotation = HistoryRotation {
+            max_bytes: 2,
+            compress: true,
+        };
+
+        assert_eq!(rotate_history_file(&path, rotation), Ok(None));
+
+        fs::write(&path, "[1, 2, 3]").unwrap();
+        let archive = rotate_history_file(&path, rotation).unwrap().unwrap();
+
+        assert!(!Path::new(&path).exists());
+        assert!(archive.ends_with(".json.gz"));
+        assert!(Path::new(&archive).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_message_front_matter() {
+        let contents = "---\ndelay_secs: 12\ninvite_format: card\n---\nJoin us: {link}\n";
+
+        let (settings, template) = parse_front_matter(contents);
+
+        assert_eq!(settings.delay_secs, Some(12));
+        assert_eq!(settings.invite_format, Some(InviteFormat::Card));
+        assert_eq!(template, "Join us: {link}");
+    }
+
+    #[test]
+    fn plain_templates_have_no_front_matter() {
+        let contents = "Hi!\n---\nJoin us: {link}";
+
+        let (settings, template) = parse_front_matter(contents);
+
+        assert_eq!(settings, MessageSettings::default());
+        assert_eq!(template, contents);
+    }
+
+    #[test]
+    fn falls_back_to_the_english_invite_template() {
+        let missing = "missing-message.txt";
+
+        let (_, french) = load_invite_message_template(missing, "fr-FR");
+        let (_, unknown) = load_invite_message_template(missing, "xx");
+
+        assert!(french.starts_with("Bonjour") && french.contains("{link}"));
+        assert_eq!(unknown, default_invite_template("en").unwrap());
+        for (_, template) in DEFAULT_INVITE_TEMPLATES {
+            assert!(template.contains("{link}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_current_participants_without_an_add_attempt() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let member = jid("15550000001@s.whatsapp.net");
+        let newcomer = jid("15550000002@s.whatsapp.net");
+        let mock = MockGroupOps {
+            participants: vec![member.clone()],
+            ..Default::default()
+        };
+
+        let stats =
+            add_members_batch(&mock, &group(), &[member, newcomer.clone()], &test_config()).await;
+
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![newcomer]);
+        assert_eq!(stats.total_skipped, 1);
+        assert_eq!(stats.total_success, 1);
+    }
+
+    #[tokio::test]
+    async fn never_adds_or_removes_the_group_creator() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let creator = jid("15550000021@s.whatsapp.net");
+        let member = jid("15550000022@s.whatsapp.net");
+        let mock = MockGroupOps {
+            owner: Some(creator.clone()),
+            ..Default::default()
+        };
+        let config = test_config();
+
+        let stats = add_members_batch(&mock, &group(), &[creator.clone()], &config).await;
+        assert_eq!(stats.skipped_creator, Some(creator.clone()));
+        assert!(mock.add_calls.lock().unwrap().is_empty());
+
+        let stats =
+            remove_members_batch(&mock, &group(), &[creator.clone(), member], &config).await;
+        assert_eq!(stats.owner_refused, Some(creator));
+        assert_eq!(stats.total_removed, 1);
+        assert_eq!(stats.total_failed, 1);
+    }
+
+    #[tokio::test]
+    async fn tracks_invalid_numbers_without_inviting_them() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let invalid = jid("15550000003@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(invalid.user.clone(), 400)]),
+            ..Default::default()
+        };
+
+        let stats = add_members_batch(&mock, &group(), &[invalid], &test_config()).await;
+
+        assert_eq!(stats.total_failed, 1);
+        assert_eq!(stats.invalid_phones, vec!["15550000003".to_string()]);
+        assert!(stats.failed_for_invite.is_empty());
+    }
+
+    #[tokio::test]
+    async fn treats_409_as_skipped() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let present = jid("15550000004@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(present.user.clone(), 409)]),
+            ..Default::default()
+        };
+
+        let stats = add_members_batch(&mock, &group(), &[present], &test_config()).await;
+
+        assert_eq!(stats.total_skipped, 1);
+        assert_eq!(stats.total_failed, 0);
+        assert!(stats.failed_for_invite.is_empty());
+    }
+
+    #[tokio::test]
+    async fn invites_members_that_cannot_be_added() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let private = jid("15550000005@s.whatsapp.net");
+        let unknown = jid("15550000006@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(private.user.clone(), 403), (unknown.user.clone(), 404)]),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            abort_on_not_admin: false,
+            ..test_config()
+        };
+
+        let stats = add_members_batch(
+            &mock,
+            &group(),
+            &[private.clone(), unknown.clone()],
+            &config,
+        )
+        .await;
+        assert_eq!(
+            stats.failed_for_invite,
+            vec![private.clone(), unknown.clone()]
+        );
+
+        let invite_stats =
+            send_invite_messages(&mock, &group(), &stats.failed_for_invite, &config).await;
+
+        assert_eq!(invite_stats.sent, vec![private.clone(), unknown.clone()]);
+        assert!(invite_stats.failed.is_empty());
+        let sent = mock.sent.lock().unwrap();
+        assert_eq!(sent[0].0, private);
+        assert_eq!(sent[1].0, unknown);
+        assert!(
+            sent[0]
+                .1
+                .conversation
+                .as_deref()
+                .is_some_and(|text| text.contains(&InviteLink::new(INVITE_CODE).url()))
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_when_the_first_add_is_refused_with_403() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let first = jid("15550000011@s.whatsapp.net");
+        let second = jid("15550000012@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(first.user.clone(), 403), (second.user.clone(), 403)]),
+            own_jid: Some(jid("15550000099@s.whatsapp.net")),
+            ..Default::default()
+        };
+
+        let stats =
+            add_members_batch(&mock, &group(), &[first.clone(), second], &test_config()).await;
+
+        assert!(stats.not_admin);
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![first]);
+        assert!(stats.failed_for_invite.is_empty());
+    }
+
+    #[tokio::test]
+    async fn queries_many_groups_in_order_with_their_own_errors() {
+        let groups: Vec<Jid> = (1..=6)
+            .map(|n| jid(&format!("12036300000000010{}@g.us", n)))
+            .collect();
+        let mock = MockGroupOps {
+            metadata_errors: HashSet::from([groups[2].user.clone()]),
+            ..Default::default()
+        };
+
+        let results = mock.query_many_group_metadata(&groups).await;
+
+        assert_eq!(
+            results.iter().map(|(jid, _)| jid).collect::<Vec<_>>(),
+            groups.iter().collect::<Vec<_>>()
+        );
+        for (index, (group_jid, metadata)) in results.iter().enumerate() {
+            match metadata {
+                Ok(metadata) => assert_eq!(&metadata.jid, group_jid),
+                Err(e) => {
+                    assert_eq!(index, 2);
+                    assert_eq!(e.to_string(), "item-not-found");
+                }
+            }
+        }
+        assert!(results[2].1.is_err());
+    }
+
+    #[tokio::test]
+    async fn does_not_wait_for_a_reconnect_while_connected() {
+        let member = jid("15550000020@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_failures: HashMap::from([(
+                member.user.clone(),
+                "websocket frame rejected".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let policy = RetryPolicy {
+            max_retries: 0,
+            ..RetryPolicy::default()
+        };
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            add_member_with_retry(&mock, &group(), &member, &policy),
+        )
+        .await
+        .expect("waited for a reconnect while connected");
+
+        assert!(!result.success);
+        assert_eq!(mock.add_calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn continues_after_a_privacy_403_when_admin() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let me = jid("15550000098@s.whatsapp.net");
+        let private = jid("15550000018@s.whatsapp.net");
+        let next = jid("15550000019@s.whatsapp.net");
+        let mock = MockGroupOps {
+            participants: vec![me.clone()],
+            admins: HashSet::from([me.user.clone()]),
+            own_jid: Some(me),
+            add_errors: HashMap::from([(private.user.clone(), 403)]),
+            ..Default::default()
+        };
+
+        let stats = add_members_batch(
+            &mock,
+            &group(),
+            &[private.clone(), next.clone()],
+            &test_config(),
+        )
+        .await;
+
+        assert!(!stats.not_admin);
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![private, next]);
+        assert_eq!(stats.total_failed, 1);
+        assert_eq!(stats.total_success, 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_invite_the_same_member_twice_across_runs() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let first = jid("15550000031@s.whatsapp.net");
+        let second = jid("15550000032@s.whatsapp.net");
+        let third = jid("15550000033@s.whatsapp.net");
+        let unreachable = jid("15550000034@s.whatsapp.net");
+        let mock = MockGroupOps {
+            send_errors: HashSet::from([unreachable.user.clone()]),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            max_retries: 0,
+            ..test_config()
+        };
+        let _ = fs::remove_file(output_path("invites_sent.json"));
+
+        let first_run = send_invite_messages(
+            &mock,
+            &group(),
+            &[first.clone(), second.clone(), unreachable.clone()],
+            &config,
+        )
+        .await;
+        assert_eq!(first_run.sent, vec![first.clone(), second.clone()]);
+        assert_eq!(first_run.failed.len(), 1);
+        assert_eq!(first_run.failed[0].0, unreachable);
+
+        let second_run = send_invite_messages(
+            &mock,
+            &group(),
+            &[second.clone(), third.clone(), first.clone()],
+            &config,
+        )
+        .await;
+        assert_eq!(second_run.sent, vec![third.clone()]);
+        assert_eq!(second_run.skipped, vec![second, first]);
+
+        let recorded: Vec<String> =
+            serde_json::from_str(&fs::read_to_string(output_path("invites_sent.json")).unwrap())
+                .unwrap();
+        assert_eq!(
+            recorded,
+            vec![
+                "15550000031".to_string(),
+                "15550000032".to_string(),
+                "15550000033".to_string()
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_adds_missing_from_the_group_afterwards() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let kept = jid("15550000041@s.whatsapp.net");
+        let dropped = jid("15550000042@s.whatsapp.net");
+        let mock = MockGroupOps {
+            dropped_adds: HashSet::from([dropped.user.clone()]),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            verify_adds: true,
+            ..test_config()
+        };
+
+        let stats =
+            add_members_batch(&mock, &group(), &[kept.clone(), dropped.clone()], &config).await;
+
+        assert_eq!(stats.added_jids, vec![kept, dropped.clone()]);
+        assert_eq!(stats.unconfirmed_adds, vec![dropped]);
+    }
+
+    #[tokio::test]
+    async fn refuses_members_without_consent() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let consented = jid("15550000051@s.whatsapp.net");
+        let unconsented = jid("15550000052@s.whatsapp.net");
+        let mock = MockGroupOps::default();
+        let config = RunConfig {
+            consented_phones: Some(HashSet::from(["15550000051".to_string()])),
+            ..test_config()
+        };
+
+        let stats = add_members_chunked(
+            &mock,
+            &group(),
+            &[unconsented.clone(), consented.clone()],
+            2,
+            &config,
+        )
+        .await;
+
+        assert_eq!(*mock.add_calls.lock().unwrap(), vec![consented]);
+        assert_eq!(stats.total_success, 1);
+        assert_eq!(stats.total_no_consent, 1);
+    }
+
+    #[tokio::test]
+    async fn appends_every_add_attempt_to_the_audit_log() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let added = jid("15550000061@s.whatsapp.net");
+        let unknown = jid("15550000062@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(unknown.user.clone(), 404)]),
+            ..Default::default()
+        };
+        let config = test_config();
+        let audit_log = output_path("audit.log");
+        let _ = fs::remove_file(&audit_log);
+        let config = RunConfig {
+            audit_log: Some(audit_log.clone()),
+            ..config
+        };
+
+        add_members_batch(&mock, &group(), &[added.clone()], &config).await;
+        add_members_batch(&mock, &group(), &[unknown.clone()], &config).await;
+
+        let events: Vec<serde_json::Value> = fs::read_to_string(&audit_log)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["jid"], added.to_string());
+        assert_eq!(events[0]["result"], "added");
+        assert_eq!(events[1]["jid"], unknown.to_string());
+        assert_eq!(events[1]["result"], "failed");
+        assert_eq!(events[1]["error_code"], 404);
+    }
+
+    #[tokio::test]
+    async fn finds_participants_no_longer_on_whatsapp() {
+        let active = jid("15550000071@s.whatsapp.net");
+        let ghost = jid("15550000072@s.whatsapp.net");
+        let lid = jid("123456789012345@lid");
+        let mock = MockGroupOps {
+            participants: vec![active, ghost.clone(), lid.clone()],
+            unregistered: HashSet::from([ghost.user.clone(), lid.user.clone()]),
+            ..Default::default()
+        };
+
+        let ghosts = find_ghost_members(&mock, &group()).await.unwrap();
+
+        assert_eq!(ghosts, vec![ghost]);
+    }
+
+    #[tokio::test]
+    async fn stops_after_too_many_failures_in_a_row() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let members: Vec<Jid> = (81..=85)
+            .map(|n| jid(&format!("155500000{}@s.whatsapp.net", n)))
+            .collect();
+        // The second member's success resets the count, so the fourth trips the breaker
+        let mock = MockGroupOps {
+            add_errors: [81, 83, 84, 85]
+                .iter()
+                .map(|n| (format!("155500000{}", n), 404))
+                .collect(),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            max_consecutive_failures: Some(2),
+            ..test_config()
+        };
+
+        let stats = add_members_batch(&mock, &group(), &members, &config).await;
+
+        assert!(stats.too_many_failures);
+        assert_eq!(mock.add_calls.lock().unwrap().len(), 4);
+        assert_eq!(stats.total_success, 1);
+        assert_eq!(stats.total_failed, 3);
+    }
+
+    #[tokio::test]
+    async fn records_chunk_members_added_before_the_breaker_trips() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let members: Vec<Jid> = (86..=89)
+            .map(|n| jid(&format!("155500000{}@s.whatsapp.net", n)))
+            .collect();
+        // The second failure trips the breaker while the third member is already added
+        let mock = MockGroupOps {
+            add_errors: [86, 87]
+                .iter()
+                .map(|n| (format!("155500000{}", n), 404))
+                .collect(),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            max_consecutive_failures: Some(2),
+            ..test_config()
+        };
+
+        let stats = add_members_chunked(&mock, &group(), &members, 3, &config).await;
+
+        assert!(stats.too_many_failures);
+        assert_eq!(*mock.add_calls.lock().unwrap(), members[..3].to_vec());
+        assert_eq!(stats.added_jids, vec![members[2].clone()]);
+        assert_eq!(stats.total_failed, 2);
+    }
+
+    #[tokio::test]
+    async fn reuses_the_invite_link_until_forgotten() {
+        // A group of its own, so other tests don't share its cache entry
+        let group_jid = jid("120363000000000091@g.us");
+        let mock = MockGroupOps::default();
+        let queries = || {
+            mock.invite_link_queries
+                .load(std::sync::atomic::Ordering::Relaxed)
+        };
+
+        cached_invite_link(&mock, &group_jid).await.unwrap();
+        let link = cached_invite_link(&mock, &group_jid).await.unwrap();
+        assert_eq!(link, InviteLink::new(INVITE_CODE));
+        assert_eq!(queries(), 1);
+
+        forget_invite_link(&group_jid);
+        cached_invite_link(&mock, &group_jid).await.unwrap();
+        assert_eq!(queries(), 2);
+    }
+
+    #[tokio::test]
+    async fn posts_one_welcome_message_with_the_added_count() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let mock = MockGroupOps::default();
+        let config = RunConfig {
+            welcome_message: Some("Welcome to our {count} new members!\n".to_string()),
+            ..test_config()
+        };
+
+        add_members_batch(
+            &mock,
+            &group(),
+            &[
+                jid("15550000007@s.whatsapp.net"),
+                jid("15550000008@s.whatsapp.net"),
+            ],
+            &config,
+        )
+        .await;
+
+        let sent = mock.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, group());
+        assert_eq!(
+            sent[0].1.conversation.as_deref(),
+            Some("Welcome to our 2 new members!")
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_members_added_by_an_earlier_run() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let member = jid("15550000009@s.whatsapp.net");
+        let first_run = MockGroupOps::default();
+        add_members_batch(&first_run, &group(), &[member.clone()], &test_config()).await;
+        assert_eq!(*first_run.add_calls.lock().unwrap(), vec![member.clone()]);
+
+        // A fresh group state, so only added_members.json knows about the member
+        let second_run = MockGroupOps::default();
+        let config = RunConfig {
+            skip_previously_added: true,
+            ..test_config()
+        };
+        let stats = add_members_batch(&second_run, &group(), &[member], &config).await;
+
+        assert_eq!(stats.total_skipped, 1);
+        assert!(second_run.add_calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn spreads_adds_evenly_over_the_window() {
+        assert_eq!(spread_delay(Duration::from_secs(6 * 3600), 201), 108);
+        assert_eq!(spread_delay(Duration::from_secs(60), 1), 60);
+        assert_eq!(spread_delay(Duration::from_secs(10), 1000), 1);
+    }
+
+    #[test]
+    fn grades_rate_limit_risk_by_threshold() {
+        let thresholds = RiskThresholds::default();
+
+        let risky = estimate_run(500, 5, None, &thresholds);
+        assert_eq!(risky.duration, Duration::from_secs(2500));
+        assert_eq!(risky.risk, RiskLevel::High);
+        assert_eq!(risky.advice.len(), 2);
+
+        assert_eq!(estimate_run(50, 20, None, &thresholds).risk, RiskLevel::Low);
+        let recent = Some(Duration::from_secs(3600));
+        assert_eq!(
+            estimate_run(50, 20, recent, &thresholds).risk,
+            RiskLevel::Medium
+        );
+    }
+
+    #[test]
+    fn queues_retryable_failures_and_clears_processed_members() {
+        // Points the output directory at the per-process test directory
+        test_config();
+        let group = jid("120363000000000099@g.us");
+        let rate_limited = jid("15550000010@s.whatsapp.net");
+        let recovered = jid("15550000011@s.whatsapp.net");
+        let not_on_whatsapp = jid("15550000012@s.whatsapp.net");
+        let refused = jid("15550000017@s.whatsapp.net");
+
+        let mut first = RunReport::new(&group, None);
+        let mut result = AddMemberResult::new(recovered.clone());
+        result.error_code = Some(429);
+        first.record(&result);
+        update_retry_queue(&group, &first).unwrap();
+        assert_eq!(load_retry_queue(&group), vec![recovered.clone()]);
+
+        let mut second = RunReport::new(&group, None);
+        let mut result = AddMemberResult::new(rate_limited.clone());
+        result.error_code = Some(429);
+        second.record(&result);
+        let mut result = AddMemberResult::new(recovered.clone());
+        result.success = true;
+        second.record(&result);
+        let mut result = AddMemberResult::new(not_on_whatsapp);
+        result.error_code = Some(404);
+        second.record(&result);
+        // Privacy settings refuse the add, which won't change on retry
+        let mut result = AddMemberResult::new(refused);
+        result.error_code = Some(403);
+        second.record(&result);
+
+        assert_eq!(update_retry_queue(&group, &second), Ok(1));
+        assert_eq!(load_retry_queue(&group), vec![rate_limited]);
+    }
+
+    #[tokio::test]
+    async fn verify_409_retries_once_when_the_member_is_absent() {
+        let stale = jid("15550000013@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([(stale.user.clone(), 409)]),
+            ..Default::default()
+        };
+        let policy = RetryPolicy {
+            verify_409: true,
+            ..RetryPolicy::default()
+        };
+
+        let result = add_member_with_retry(&mock, &group(), &stale, &policy).await;
+
+        assert!(result.skipped);
+        assert_eq!(mock.add_calls.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn chunked_add_retries_members_without_a_final_answer_alone() {
+        let _lock = OUTPUT_FILES_LOCK.lock().await;
+        let added = jid("15550000014@s.whatsapp.net");
+        let unknown = jid("15550000015@s.whatsapp.net");
+        let rate_limited = jid("15550000016@s.whatsapp.net");
+        let mock = MockGroupOps {
+            add_errors: HashMap::from([
+                (unknown.user.clone(), 404),
+                (rate_limited.user.clone(), 429),
+            ]),
+            ..Default::default()
+        };
+        let config = RunConfig {
+            max_retries: 0,
+            ..test_config()
+        };
+        let members = [added.clone(), unknown.clone(), rate_limited.clone()];
+
+        let stats = add_members_chunked(&mock, &group(), &members, 3, &config).await;
+
+        assert_eq!(
+            *mock.add_calls.lock().unwrap(),
+            vec![
+                added.clone(),
+                unknown.clone(),
+                rate_limited.clone(),
+                rate_limited
+            ]
+        );
+        assert_eq!(stats.added_jids, vec![added]);
+        assert_eq!(stats.failed_for_invite, vec![unknown]);
+        assert_eq!(stats.total_failed, 2);
+    }
+
+    #[cfg(feature = "e164")]
+    #[test]
+    fn rejects_numbers_outside_the_numbering_plan() {
+        let entries = vec![
+            "+33 6 12 34 56 78".to_string(),
+            "+999 1234 5678".to_string(),
+        ];
+
+        let (jids, rejected) = convert_member_list(&entries, None);
+
+        assert_eq!(jids, vec![jid("33612345678@s.whatsapp.net")]);
+        assert_eq!(
+            rejected,
+            vec![("+999 1234 5678".to_string(), "invalid number".to_string())]
+        );
+    }
 }
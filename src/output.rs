@@ -0,0 +1,85 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSONL_MODE: AtomicBool = AtomicBool::new(false);
+static QUIET_MODE: AtomicBool = AtomicBool::new(false);
+static COLOR_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Switch to JSON-lines output: stdout only carries JSON objects
+pub fn set_jsonl(enabled: bool) {
+    JSONL_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether JSON-lines output is enabled
+pub fn jsonl_enabled() -> bool {
+    JSONL_MODE.load(Ordering::Relaxed)
+}
+
+/// Suppress per-member progress lines, keeping summaries and errors
+pub fn set_quiet(enabled: bool) {
+    QUIET_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether per-member progress lines are suppressed
+pub fn quiet_enabled() -> bool {
+    QUIET_MODE.load(Ordering::Relaxed)
+}
+
+/// Colorize success and failure lines with ANSI escapes
+pub fn set_color(enabled: bool) {
+    COLOR_MODE.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether ANSI colors are used
+pub fn color_enabled() -> bool {
+    COLOR_MODE.load(Ordering::Relaxed)
+}
+
+/// `text` in green when colors are enabled
+pub fn green(text: &str) -> String {
+    paint(text, "32")
+}
+
+/// `text` in red when colors are enabled
+pub fn red(text: &str) -> String {
+    paint(text, "31")
+}
+
+fn paint(text: &str, code: &str) -> String {
+    if color_enabled() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// Print one JSON object on its own line to stdout (JSON-lines mode only)
+pub fn emit_jsonl(record: &serde_json::Value) {
+    if jsonl_enabled() {
+        println!("{}", record);
+    }
+}
+
+/// Print a human-readable line
+///
+/// Goes to stdout normally and to stderr in JSON-lines mode, so stdout stays
+/// machine-readable.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::output::jsonl_enabled() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Print a per-member progress line, unless `--quiet` is set
+#[macro_export]
+macro_rules! progress {
+    ($($arg:tt)*) => {
+        if !$crate::output::quiet_enabled() {
+            $crate::say!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,62 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::say;
+
+/// How often a paused batch checks whether it was resumed
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a paused batch reminds that it is paused
+const PAUSE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the batch is currently paused
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+/// Flip the paused state, returning the new state
+pub fn toggle_pause() -> bool {
+    !PAUSED.fetch_xor(true, Ordering::SeqCst)
+}
+
+/// Toggle the paused state on every `SIGUSR1` (e.g. `kill -USR1 <pid>`)
+#[cfg(unix)]
+pub fn listen_for_sigusr1() {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut signals = match signal(SignalKind::user_defined1()) {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("⚠️  Failed to listen for SIGUSR1: {}", e);
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        while signals.recv().await.is_some() {
+            if toggle_pause() {
+                say!("⏸️  Paused (send SIGUSR1 again to resume)");
+            } else {
+                say!("▶️  Resumed");
+            }
+        }
+    });
+}
+
+/// Wait while the batch is paused, logging periodically
+pub async fn wait_while_paused() {
+    let mut paused_for = Duration::ZERO;
+
+    while is_paused() {
+        if paused_for
+            .as_secs()
+            .is_multiple_of(PAUSE_LOG_INTERVAL.as_secs())
+        {
+            say!("⏸️  paused ({}s so far)", paused_for.as_secs());
+        }
+        tokio::time::sleep(PAUSE_POLL_INTERVAL).await;
+        paused_for += PAUSE_POLL_INTERVAL;
+    }
+}
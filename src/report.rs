@@ -0,0 +1,282 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+
+use crate::member_utils::{AddMemberResult, jid_to_phone, now_timestamp};
+use wacore_binary::jid::Jid;
+
+/// Default path of the human-readable run report
+pub const DEFAULT_REPORT_FILE: &str = "run_report.json";
+
+/// How long a webhook delivery may take before it's abandoned
+pub const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of one member in the run report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportEntry {
+    pub jid: String,
+    pub phone: String,
+    pub name: Option<String>,
+    /// "added", "skipped", "failed", "blocked" or "no_consent"
+    pub status: String,
+    pub error_code: Option<u64>,
+    pub error_text: Option<String>,
+    /// RFC 3339 time of the attempt; may be missing from hand-written reports
+    #[serde(default)]
+    pub timestamp: String,
+}
+
+/// Add error codes that may go away on retry (rate limited, request timed out)
+const RETRYABLE_ERROR_CODES: [u64; 2] = [429, 408];
+
+impl ReportEntry {
+    /// Whether the member failed for a reason that may go away on retry: a rate
+    /// limit, a timeout, or a network failure (no error code). Privacy refusals,
+    /// missing admin rights and invalid numbers are not retried.
+    pub fn is_retryable(&self) -> bool {
+        self.status == "failed"
+            && self
+                .error_code
+                .is_none_or(|code| RETRYABLE_ERROR_CODES.contains(&code))
+    }
+
+    /// Whether the attempt happened at or after `cutoff`, or `None` when it has no
+    /// readable timestamp
+    pub fn recorded_since(&self, cutoff: chrono::DateTime<chrono::Utc>) -> Option<bool> {
+        chrono::DateTime::parse_from_rfc3339(&self.timestamp)
+            .ok()
+            .map(|time| time >= cutoff)
+    }
+}
+
+/// Human-readable snapshot of a member-addition run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub group: String,
+    pub started_at: String,
+    /// Unset while the run is in progress
+    pub finished_at: Option<String>,
+    /// Seed of a randomized run, to replay it with `--shuffle-seed`
+    pub seed: Option<u64>,
+    /// Campaign label given with `--label` (empty when unset)
+    #[serde(default)]
+    pub label: String,
+    pub entries: Vec<ReportEntry>,
+}
+
+impl RunReport {
+    pub fn new(group_jid: &Jid, seed: Option<u64>) -> Self {
+        Self {
+            group: group_jid.to_string(),
+            started_at: now_timestamp(),
+            finished_at: None,
+            seed,
+            label: String::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Load a report written by a previous run
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+        serde_json::from_str(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))
+    }
+
+    /// Record the outcome of one add attempt
+    pub fn record(&mut self, result: &AddMemberResult) {
+        self.entries.push(ReportEntry {
+            jid: result.jid.to_string(),
+            phone: jid_to_phone(&result.jid),
+            name: result.name.clone(),
+            status: result.status().to_string(),
+            error_code: result.error_code,
+            error_text: result.error_text.clone(),
+            timestamp: now_timestamp(),
+        });
+    }
+
+    /// Record a member that was skipped because it is blocklisted
+    pub fn record_blocked(&mut self, jid: &Jid) {
+        self.record_refused(jid, "blocked", None);
+    }
+
+    /// Record a member that was refused because the consent file doesn't list it
+    pub fn record_no_consent(&mut self, jid: &Jid) {
+        self.record_refused(jid, "no_consent", Some("No consent on record"));
+    }
+
+    fn record_refused(&mut self, jid: &Jid, status: &str, error_text: Option<&str>) {
+        self.entries.push(ReportEntry {
+            jid: jid.to_string(),
+            phone: jid_to_phone(jid),
+            name: None,
+            status: status.to_string(),
+            error_code: None,
+            error_text: error_text.map(str::to_string),
+            timestamp: now_timestamp(),
+        });
+    }
+
+    /// Time between the start and the end of the run, once it is finished
+    pub fn duration(&self) -> Option<Duration> {
+        let started = chrono::DateTime::parse_from_rfc3339(&self.started_at).ok()?;
+        let finished = chrono::DateTime::parse_from_rfc3339(self.finished_at.as_deref()?).ok()?;
+        (finished - started).to_std().ok()
+    }
+
+    /// Mark the run as finished
+    pub fn finish(&mut self) {
+        self.finished_at = Some(now_timestamp());
+    }
+
+    /// Write the report to `path`
+    ///
+    /// The report is written to a temporary file first and then renamed, so an
+    /// interrupted write never leaves a truncated report behind.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json_data = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize run report: {}", e))?;
+
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, json_data)
+            .map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+    /// Write one CSV row per member, with a header
+    ///
+    /// Columns: phone, jid, status, error_code, error_text, timestamp.
+    pub fn to_csv<W: Write>(&self, mut writer: W) -> std::io::Result<()> {
+        writeln!(writer, "phone,jid,status,error_code,error_text,timestamp")?;
+
+        for entry in &self.entries {
+            let error_code = entry.error_code.map(|code| code.to_string());
+            let fields = [
+                entry.phone.as_str(),
+                entry.jid.as_str(),
+                entry.status.as_str(),
+                error_code.as_deref().unwrap_or_default(),
+                entry.error_text.as_deref().unwrap_or_default(),
+                entry.timestamp.as_str(),
+            ];
+            let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+
+        writer.flush()
+    }
+
+    /// POST the report as JSON to a webhook URL
+    ///
+    /// Blocking; gives up after `WEBHOOK_TIMEOUT` so a dead endpoint can't hang the process.
+    pub fn send_webhook(&self, url: &str) -> Result<(), String> {
+        let body = serde_json::to_string(self)
+            .map_err(|e| format!("Failed to serialize run report: {}", e))?;
+
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(WEBHOOK_TIMEOUT))
+            .build()
+            .into();
+
+        agent
+            .post(url)
+            .content_type("application/json")
+            .send(body)
+            .map_err(|e| format!("Failed to POST to {}: {}", url, e))?;
+
+        Ok(())
+    }
+
+    /// Write the report as CSV to `path`
+    pub fn save_csv(&self, path: &str) -> Result<(), String> {
+        let file =
+            fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        self.to_csv(std::io::BufWriter::new(file))
+            .map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// Counters of a finished run, exported for monitoring with `--metrics-file`
+#[derive(Debug, Clone, Default)]
+pub struct RunMetrics {
+    pub group: String,
+    /// Campaign label given with `--label`
+    pub label: String,
+    pub adds_success: usize,
+    pub adds_failed: usize,
+    /// Adds retried after a rate limit (429)
+    pub rate_limited: usize,
+    pub duration: Duration,
+}
+
+impl RunMetrics {
+    /// Render the metrics in the Prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let labels = format!(
+            "{{group=\"{}\",label=\"{}\"}}",
+            escape_label_value(&self.group),
+            escape_label_value(&self.label)
+        );
+        let metrics = [
+            (
+                "wa_adds_success_total",
+                "counter",
+                "Members added to the group",
+                self.adds_success.to_string(),
+            ),
+            (
+                "wa_adds_failed_total",
+                "counter",
+                "Members that could not be added",
+                self.adds_failed.to_string(),
+            ),
+            (
+                "wa_rate_limited_total",
+                "counter",
+                "Adds retried after a rate limit",
+                self.rate_limited.to_string(),
+            ),
+            (
+                "wa_run_duration_seconds",
+                "gauge",
+                "Duration of the run",
+                format!("{:.3}", self.duration.as_secs_f64()),
+            ),
+        ];
+
+        let mut text = String::new();
+        for (name, kind, help, value) in metrics {
+            text.push_str(&format!("# HELP {} {}\n", name, help));
+            text.push_str(&format!("# TYPE {} {}\n", name, kind));
+            text.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+        text
+    }
+
+    /// Write the metrics to `path`, atomically so a collector never reads a partial file
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, self.to_prometheus())
+            .map_err(|e| format!("Failed to write {}: {}", tmp_path, e))?;
+        fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+}
+
+/// Escape a Prometheus label value
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Quote a CSV field when it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,249 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::Sha256;
+use std::fs;
+use std::path::Path;
+
+/// First bytes of every exported session, followed by the format version
+const MAGIC: &[u8] = b"WAINVSESS";
+const FORMAT_VERSION: u8 = 1;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// PBKDF2-HMAC-SHA256 rounds used to derive the key from the passphrase
+const KDF_ROUNDS: u32 = 600_000;
+
+/// Shortest passphrase accepted for an export
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+
+/// Files making up a SQLite session store, as suffixes of the database path
+///
+/// The write-ahead log may hold credentials not yet checkpointed into the main
+/// file, so it travels with it.
+pub const DB_FILE_SUFFIXES: &[&str] = &["", "-wal", "-shm"];
+
+/// Suffix of the files an import writes before renaming them into place
+const IMPORT_TMP_SUFFIX: &str = ".import-tmp";
+
+/// Encrypt the session database at `db_path` into `out_path`
+///
+/// Returns the number of database files exported.
+pub fn export_session(db_path: &str, out_path: &str, passphrase: &str) -> Result<usize, String> {
+    if passphrase.len() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "Passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+
+    let mut files = Vec::new();
+    for suffix in DB_FILE_SUFFIXES {
+        let path = format!("{}{}", db_path, suffix);
+        if !Path::new(&path).exists() {
+            continue;
+        }
+        let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        files.push((suffix.to_string(), data));
+    }
+    if files.iter().all(|(suffix, _)| !suffix.is_empty()) {
+        return Err(format!("No session found at {}", db_path));
+    }
+
+    let blob = seal(&pack_files(&files), passphrase)?;
+    fs::write(out_path, blob).map_err(|e| format!("Failed to write {}: {}", out_path, e))?;
+    Ok(files.len())
+}
+
+/// Decrypt an exported session from `blob_path` into a fresh store at `db_path`
+///
+/// Refuses to overwrite an existing session unless `force` is set. The files are
+/// written next to `db_path` first and renamed into place, so a failed import
+/// leaves the old session intact; stale `-wal`/`-shm` files are removed.
+pub fn import_session(
+    blob_path: &str,
+    db_path: &str,
+    passphrase: &str,
+    force: bool,
+) -> Result<usize, String> {
+    if Path::new(db_path).exists() && !force {
+        return Err(format!(
+            "{} already exists; pass --force to replace that session",
+            db_path
+        ));
+    }
+
+    let blob = fs::read(blob_path).map_err(|e| format!("Failed to read {}: {}", blob_path, e))?;
+    let files = unpack_files(&open(&blob, passphrase)?)?;
+
+    let mut written = Vec::new();
+    for (suffix, data) in &files {
+        let tmp_path = format!("{}{}{}", db_path, suffix, IMPORT_TMP_SUFFIX);
+        if let Err(e) = fs::write(&tmp_path, data) {
+            for tmp_path in written.iter().chain([&tmp_path]) {
+                let _ = fs::remove_file(tmp_path);
+            }
+            return Err(format!("Failed to write {}: {}", tmp_path, e));
+        }
+        written.push(tmp_path);
+    }
+
+    for suffix in DB_FILE_SUFFIXES {
+        let path = format!("{}{}", db_path, suffix);
+        if files.iter().any(|(imported, _)| imported == suffix) {
+            let tmp_path = format!("{}{}", path, IMPORT_TMP_SUFFIX);
+            fs::rename(&tmp_path, &path)
+                .map_err(|e| format!("Failed to move {} into place: {}", tmp_path, e))?;
+        } else if Path::new(&path).exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path, e))?;
+        }
+    }
+
+    Ok(files.len())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Key<Aes256Gcm> {
+    let mut key = Key::<Aes256Gcm>::default();
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, KDF_ROUNDS, &mut key);
+    key
+}
+
+/// Encrypt `payload`: magic, version, salt and nonce, then the AES-256-GCM ciphertext
+fn seal(payload: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let salt: [u8; SALT_LEN] = rand::random();
+    let nonce: [u8; NONCE_LEN] = rand::random();
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, &salt));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), payload)
+        .map_err(|_| "Failed to encrypt the session".to_string())?;
+
+    let mut blob = Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.push(FORMAT_VERSION);
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+fn open(blob: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    let header_len = MAGIC.len() + 1 + SALT_LEN + NONCE_LEN;
+    if blob.len() < header_len || !blob.starts_with(MAGIC) {
+        return Err("Not an exported session file".to_string());
+    }
+    if blob[MAGIC.len()] != FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported session export version {}",
+            blob[MAGIC.len()]
+        ));
+    }
+
+    let (salt, rest) = blob[MAGIC.len() + 1..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+
+    let cipher = Aes256Gcm::new(&derive_key(passphrase, salt));
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "Wrong passphrase or corrupted session file".to_string())
+}
+
+/// Serialize `(suffix, contents)` pairs as length-prefixed records
+fn pack_files(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (suffix, data) in files {
+        payload.push(suffix.len() as u8);
+        payload.extend_from_slice(suffix.as_bytes());
+        payload.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        payload.extend_from_slice(data);
+    }
+    payload
+}
+
+fn unpack_files(mut payload: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let truncated = || "Truncated session payload".to_string();
+    let mut files = Vec::new();
+
+    while let Some((&suffix_len, rest)) = payload.split_first() {
+        let suffix = rest.get(..suffix_len as usize).ok_or_else(truncated)?;
+        let suffix = String::from_utf8(suffix.to_vec()).map_err(|_| truncated())?;
+        if !DB_FILE_SUFFIXES.contains(&suffix.as_str()) {
+            return Err(format!("Unexpected session file suffix \"{}\"", suffix));
+        }
+        let rest = &rest[suffix_len as usize..];
+
+        let len_bytes = rest.get(..8).ok_or_else(truncated)?;
+        // The length comes from the archive, so it must not be trusted to fit
+        let end = usize::try_from(u64::from_le_bytes(len_bytes.try_into().unwrap()))
+            .ok()
+            .and_then(|len| len.checked_add(8))
+            .ok_or_else(truncated)?;
+        let data = rest.get(8..end).ok_or_else(truncated)?;
+
+        files.push((suffix, data.to_vec()));
+        payload = &rest[end..];
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sealed_sessions_only_open_with_the_passphrase() {
+        let files = vec![
+            (String::new(), b"SQLite format 3".to_vec()),
+            ("-wal".to_string(), vec![1, 2, 3]),
+        ];
+        let blob = seal(&pack_files(&files), "correct horse").unwrap();
+
+        assert!(open(&blob, "wrong horse").is_err());
+        let payload = open(&blob, "correct horse").unwrap();
+        assert_eq!(unpack_files(&payload).unwrap(), files);
+    }
+
+    #[test]
+    fn rejects_file_lengths_past_the_payload() {
+        for len in [4, u64::MAX - 7, u64::MAX] {
+            let mut payload = vec![0];
+            payload.extend_from_slice(&len.to_le_bytes());
+            payload.extend_from_slice(b"abc");
+
+            assert_eq!(
+                unpack_files(&payload),
+                Err("Truncated session payload".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn imports_an_exported_session_over_an_old_one() {
+        let dir =
+            std::env::temp_dir().join(format!("whatsapp-session-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.db").to_string_lossy().to_string();
+        let target = dir.join("target.db").to_string_lossy().to_string();
+        let blob_path = dir.join("session.bin").to_string_lossy().to_string();
+
+        fs::write(&source, b"SQLite format 3 new").unwrap();
+        fs::write(format!("{}-wal", source), [4, 5, 6]).unwrap();
+        fs::write(&target, b"SQLite format 3 old").unwrap();
+        fs::write(format!("{}-shm", target), [9]).unwrap();
+
+        assert_eq!(export_session(&source, &blob_path, "correct horse"), Ok(2));
+        assert!(import_session(&blob_path, &target, "correct horse", false).is_err());
+        assert_eq!(
+            import_session(&blob_path, &target, "correct horse", true),
+            Ok(2)
+        );
+
+        assert_eq!(fs::read(&target).unwrap(), b"SQLite format 3 new");
+        assert_eq!(fs::read(format!("{}-wal", target)).unwrap(), [4, 5, 6]);
+        assert!(!Path::new(&format!("{}-shm", target)).exists());
+        assert!(!Path::new(&format!("{}{}", target, IMPORT_TMP_SUFFIX)).exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}